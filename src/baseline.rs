@@ -0,0 +1,213 @@
+use crate::{
+    baseline_types::{Baseline, BaselineEntry, BaselineOutcome},
+    diff::fingerprinted_todos,
+    diff_types::TodoStatus,
+    path_analyzer_types::AnalysisResult,
+    utils::write_json,
+};
+use anyhow::{Context, Result};
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+
+/// Used by `baseline create` and `check` unless another file is given.
+pub const DEFAULT_BASELINE_PATH: &str = ".todolint-baseline.json";
+
+const BASELINE_VERSION: u32 = 1;
+
+/// Baseline of every invalid todo in `analysis`.
+pub fn create_baseline(analysis: &AnalysisResult) -> Baseline {
+    let mut entries: Vec<BaselineEntry> = fingerprinted_todos(analysis)
+        .into_iter()
+        .filter(|todo| todo.status == TodoStatus::Invalid)
+        .map(|todo| BaselineEntry {
+            fingerprint: todo.fingerprint,
+            path: todo.path,
+            line: todo.line,
+            text: todo.text,
+        })
+        .collect();
+    entries.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+    Baseline {
+        version: BASELINE_VERSION,
+        entries,
+    }
+}
+
+pub fn load_baseline(path: &Path) -> Result<Baseline> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open baseline '{}'", path.display()))?;
+    let baseline: Baseline = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to parse baseline '{}'", path.display()))?;
+
+    if baseline.version > BASELINE_VERSION {
+        anyhow::bail!(
+            "Baseline has version {}, but only up to {} is supported",
+            baseline.version,
+            BASELINE_VERSION
+        );
+    }
+    Ok(baseline)
+}
+
+pub fn save_baseline(baseline: &Baseline, path: &Path) -> Result<()> {
+    let mut file = File::create(path)
+        .with_context(|| format!("Failed to create baseline '{}'", path.display()))?;
+    write_json(&mut file, baseline)
+}
+
+/// Removes baselined invalid todos from `analysis`. Each entry suppresses one todo with its
+/// fingerprint, so a baselined todo that gets copied still fails for the copy. `scope` is the part
+/// of the project that was scanned, see `tighten_ratchet`; entries outside of it aren't stale.
+pub fn apply_baseline(
+    analysis: &mut AnalysisResult,
+    baseline: &Baseline,
+    scope: &Path,
+) -> BaselineOutcome {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for entry in &baseline.entries {
+        *remaining.entry(&entry.fingerprint).or_default() += 1;
+    }
+
+    let mut outcome = BaselineOutcome::default();
+    for file_analysis in analysis.file_analyses_mut() {
+        let path = file_analysis.metadata.stable_path();
+        if let Some(invalids) = file_analysis.invalids.as_mut() {
+            invalids.retain(
+                |invalid| match remaining.get_mut(&invalid.fingerprint(&path)[..]) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        outcome.suppressed += 1;
+                        false
+                    }
+                    _ => true,
+                },
+            );
+            if invalids.is_empty() {
+                file_analysis.invalids = None;
+            }
+        }
+    }
+
+    for entry in baseline
+        .entries
+        .iter()
+        .filter(|entry| entry.path.starts_with(scope))
+    {
+        if let Some(count) = remaining.get_mut(&entry.fingerprint[..]) {
+            if *count > 0 {
+                *count -= 1;
+                outcome.stale.push(entry.clone());
+            }
+        }
+    }
+
+    outcome
+}
+
+/// Removes `stale` entries from the baseline, leaving the rest as they were.
+pub fn prune_baseline(baseline: &mut Baseline, stale: &[BaselineEntry]) {
+    for entry in stale {
+        if let Some(index) = baseline.entries.iter().position(|other| other == entry) {
+            baseline.entries.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_lines,
+        path_analyzer_types::{FileAnalysis, FileMetadata},
+    };
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    fn analysis_of(content: &str) -> AnalysisResult {
+        analysis_at("./src/lib.rs", None, content)
+    }
+
+    fn analysis_at(filepath: &str, repo_path: Option<&str>, content: &str) -> AnalysisResult {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from(filepath),
+            repo_path: repo_path.map(PathBuf::from),
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        AnalysisResult::File(analyze_lines(metadata, content.as_bytes(), None).unwrap())
+    }
+
+    fn invalid_texts(file_analysis: &FileAnalysis) -> Vec<&str> {
+        file_analysis
+            .invalids
+            .iter()
+            .flatten()
+            .map(|invalid| &invalid.line_info.full_text[..])
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_baseline() {
+        let baseline = create_baseline(&analysis_of(
+            "// todo(): old\n// todo(1)(2): gone\n// todo: fine\n",
+        ));
+        assert_eq!(baseline.entries.len(), 2);
+        assert_eq!(baseline.entries[0].path, PathBuf::from("src/lib.rs"));
+
+        let mut analysis =
+            analysis_of("// todo: fine\n// todo(): old\n// todo(): new\n// todo(): old\n");
+        let outcome = apply_baseline(&mut analysis, &baseline, Path::new(""));
+
+        assert_eq!(outcome.suppressed, 1);
+        assert_eq!(outcome.stale.len(), 1);
+        assert_eq!(outcome.stale[0].line, 2);
+        assert_eq!(
+            invalid_texts(&analysis.file_analyses()[0]),
+            vec!["// todo(): new", "// todo(): old"]
+        );
+    }
+
+    #[test]
+    fn test_apply_baseline_from_another_directory() {
+        let content = "// todo(): old\n";
+        let baseline = create_baseline(&analysis_at("./src/lib.rs", Some("src/lib.rs"), content));
+        assert_eq!(baseline.entries[0].path, PathBuf::from("src/lib.rs"));
+
+        // Scanned from within `src`, or as the staged content
+        for (filepath, repo_path) in [("lib.rs", Some("src/lib.rs")), ("src/lib.rs", None)] {
+            let mut analysis = analysis_at(filepath, repo_path, content);
+            let outcome = apply_baseline(&mut analysis, &baseline, Path::new(""));
+            assert_eq!(outcome.suppressed, 1);
+            assert!(outcome.stale.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_prune_baseline_of_partial_scan() {
+        let mut baseline = create_baseline(&analysis_at(
+            "src/lib.rs",
+            Some("src/lib.rs"),
+            "// todo(): kept\n// todo(): fixed\n",
+        ));
+        let docs = analysis_at("docs/b.rs", Some("docs/b.rs"), "// todo(): b\n");
+        baseline.entries.extend(create_baseline(&docs).entries);
+
+        // `docs` wasn't scanned, so its entry isn't stale
+        let mut analysis = analysis_at("src/lib.rs", Some("src/lib.rs"), "// todo(): kept\n");
+        let outcome = apply_baseline(&mut analysis, &baseline, Path::new("src"));
+        assert_eq!(outcome.suppressed, 1);
+        assert_eq!(outcome.stale.len(), 1);
+        assert_eq!(outcome.stale[0].text, "// todo(): fixed");
+
+        prune_baseline(&mut baseline, &outcome.stale);
+        assert_eq!(
+            baseline
+                .entries
+                .iter()
+                .map(|entry| &entry.text[..])
+                .collect::<Vec<_>>(),
+            vec!["// todo(): kept", "// todo(): b"]
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Invalid todos that existed when the baseline was created. `check` doesn't fail on them, so a
+/// legacy codebase can adopt the spec for new todos first.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Baseline {
+    pub version: u32,
+    /// Sorted by path and line, to keep diffs of the checked-in file readable.
+    pub entries: Vec<BaselineEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BaselineEntry {
+    /// Matched against the todos of a scan. Path and line are only informational.
+    pub fingerprint: String,
+    pub path: PathBuf,
+    pub line: usize,
+    pub text: String,
+}
+
+/// Result of applying a baseline to a scan.
+#[derive(Debug, Default)]
+pub struct BaselineOutcome {
+    /// Invalid todos removed from the scan because they are baselined.
+    pub suppressed: usize,
+    /// Baselined todos that no longer exist.
+    pub stale: Vec<BaselineEntry>,
+}
@@ -0,0 +1,409 @@
+use crate::{
+    blame_cache::BlameCache,
+    blame_types::{BlameConfig, BlamedLine, Hunk},
+    cli::BlameArgs,
+    path_analyzer_types::FileBlameContext,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use git2::{Blame, BlameHunk, BlameOptions, DiffOptions, ObjectType, Oid, Patch, Repository};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Read when neither `--ignore-revs-file` nor git's `blame.ignoreRevsFile` is set, like GitHub
+/// does.
+pub const DEFAULT_IGNORE_REVS_PATH: &str = ".git-blame-ignore-revs";
+
+pub fn blame_config(repo: &Repository, args: &BlameArgs) -> Result<BlameConfig> {
+    let resolve = |rev: &str| {
+        repo.revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .with_context(|| format!("Failed to resolve revision '{}'", rev))
+    };
+
+    let mut ignore_revs = match ignore_revs_file(repo, args)? {
+        Some(path) => load_ignore_revs(&path)?,
+        None => HashSet::new(),
+    };
+    for rev in &args.ignore_revs {
+        ignore_revs.insert(resolve(rev)?);
+    }
+
+    Ok(BlameConfig {
+        ignore_revs,
+        track_copies: args.blame_copies,
+        first_parent: args.blame_first_parent,
+        ignore_whitespace: args.blame_ignore_whitespace,
+        oldest_commit: args.blame_since.as_deref().map(resolve).transpose()?,
+    })
+}
+
+/// The file given on the command line must exist, the configured or default one may not.
+fn ignore_revs_file(repo: &Repository, args: &BlameArgs) -> Result<Option<PathBuf>> {
+    if args.no_ignore_revs_file {
+        return Ok(None);
+    }
+    if let Some(path) = &args.ignore_revs_file {
+        return Ok(Some(path.clone()));
+    }
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir,
+        None => return Ok(None),
+    };
+
+    let configured = repo.config()?.get_path("blame.ignoreRevsFile").ok();
+    let path = workdir.join(configured.unwrap_or_else(|| PathBuf::from(DEFAULT_IGNORE_REVS_PATH)));
+    Ok(path.is_file().then_some(path))
+}
+
+/// Reads commits to ignore in the format of git's `blame.ignoreRevsFile`: one full commit hash per
+/// line, with `#` starting a comment.
+pub fn load_ignore_revs(path: &Path) -> Result<HashSet<Oid>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ignore revs file '{}'", path.display()))?;
+
+    let mut ignore_revs = HashSet::new();
+    for (index, line) in content.lines().enumerate() {
+        let rev = line.split('#').next().unwrap_or_default().trim();
+        if rev.is_empty() {
+            continue;
+        }
+        // Abbreviated hashes would be padded with zeros by `Oid::from_str`
+        if rev.len() != 40 {
+            bail!(
+                "{}:{}: expected a full commit hash, found '{}'",
+                path.display(),
+                index + 1,
+                rev
+            );
+        }
+        ignore_revs.insert(Oid::from_str(rev).with_context(|| {
+            format!(
+                "{}:{}: invalid commit hash '{}'",
+                path.display(),
+                index + 1,
+                rev
+            )
+        })?);
+    }
+    Ok(ignore_revs)
+}
+
+impl BlameConfig {
+    pub fn options(&self) -> BlameOptions {
+        let mut options = BlameOptions::new();
+        options
+            .track_copies_same_file(self.track_copies)
+            .track_copies_same_commit_moves(self.track_copies)
+            .track_copies_same_commit_copies(self.track_copies)
+            .first_parent(self.first_parent)
+            .ignore_whitespace(self.ignore_whitespace);
+        if let Some(oldest_commit) = self.oldest_commit {
+            options.oldest_commit(oldest_commit);
+        }
+        options
+    }
+
+    /// Identifies the options that change the hunks of a blame, so cached hunks are only reused
+    /// with the same ones. Ignored revisions are applied on top of the hunks, so they don't count.
+    pub fn cache_key(&self) -> String {
+        format!(
+            "track-copies={} first-parent={} ignore-whitespace={} oldest-commit={}",
+            self.track_copies,
+            self.first_parent,
+            self.ignore_whitespace,
+            self.oldest_commit
+                .map(|oldest_commit| oldest_commit.to_string())
+                .unwrap_or_default()
+        )
+    }
+}
+
+impl Hunk {
+    pub fn new(hunk: &BlameHunk) -> Self {
+        Hunk {
+            commit_id: hunk.final_commit_id().to_string(),
+            path: hunk.path().map(Path::to_path_buf),
+            start_line: hunk.final_start_line(),
+            lines: hunk.lines_in_hunk(),
+            orig_start_line: hunk.orig_start_line(),
+            boundary: hunk.is_boundary(),
+        }
+    }
+
+    /// Every line of `content`, blamed on the zero commit.
+    fn not_committed_yet(path: &Path, content: &[u8]) -> Self {
+        Hunk {
+            commit_id: Oid::zero().to_string(),
+            path: Some(path.to_path_buf()),
+            start_line: 1,
+            lines: content.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            orig_start_line: 1,
+            boundary: false,
+        }
+    }
+
+    fn contains(&self, line_number: usize) -> bool {
+        (self.start_line..self.start_line + self.lines).contains(&line_number)
+    }
+
+    fn blame_line(&self, line_number: usize) -> Result<BlamedLine> {
+        Ok(BlamedLine {
+            commit_id: Oid::from_str(&self.commit_id)?,
+            path: self.path.clone(),
+            line: self.orig_start_line + (line_number - self.start_line),
+            boundary: self.boundary,
+        })
+    }
+}
+
+pub fn hunks_of(blame: &Blame) -> Vec<Hunk> {
+    blame.iter().map(|hunk| Hunk::new(&hunk)).collect()
+}
+
+impl<'repo> FileBlameContext<'repo> {
+    /// Blames `content`, the working tree or staged content of the file at `path` (relative to
+    /// the repository root). Lines that differ from HEAD are blamed on a zero commit id, and so is
+    /// every line of a file that isn't in HEAD or before the first commit. Unmodified files are
+    /// taken from `cache` while their blame there is still valid.
+    pub fn for_working_tree(
+        repo: &'repo Repository,
+        path: &Path,
+        content: &[u8],
+        modified: DateTime<Utc>,
+        config: &'repo BlameConfig,
+        cache: Option<&RefCell<BlameCache>>,
+    ) -> Result<Self> {
+        let head_tree = repo.head().and_then(|head| head.peel_to_tree()).ok();
+        let blob = match head_tree.and_then(|tree| tree.get_path(path).ok()) {
+            Some(entry) => entry.id(),
+            None => {
+                return Ok(FileBlameContext {
+                    repo,
+                    hunks: vec![Hunk::not_committed_yet(path, content)],
+                    config,
+                    modified: Some(modified),
+                })
+            }
+        };
+        if Oid::hash_object(ObjectType::Blob, content)? == blob {
+            let cached = cache.and_then(|cache| cache.borrow_mut().get(repo, path, blob));
+            if let Some(hunks) = cached {
+                return Ok(FileBlameContext {
+                    repo,
+                    hunks,
+                    config,
+                    modified: Some(modified),
+                });
+            }
+
+            let context = FileBlameContext::new(repo, path, config)?;
+            if let Some(cache) = cache {
+                cache
+                    .borrow_mut()
+                    .insert(repo, path, blob, context.hunks.clone());
+            }
+            return Ok(FileBlameContext {
+                modified: Some(modified),
+                ..context
+            });
+        }
+
+        // `blame_buffer` needs the blame of the committed file itself, not its hunks, so modified
+        // files are blamed again even when the committed file's hunks are cached
+        let mut blame_opts = config.options();
+        let blame = repo.blame_file(path, Some(&mut blame_opts))?;
+        if let Some(cache) = cache {
+            cache
+                .borrow_mut()
+                .insert(repo, path, blob, hunks_of(&blame));
+        }
+        let hunks = hunks_of(&blame.blame_buffer(content)?);
+        Ok(FileBlameContext {
+            repo,
+            hunks,
+            config,
+            modified: Some(modified),
+        })
+    }
+
+    /// Blames a line, 1-based. Lines last changed by an ignored commit are blamed again as of
+    /// the commit before it, until a commit that isn't ignored is found. Lines an ignored commit
+    /// added stay blamed on it, since there's nothing before them.
+    pub fn blame_line(&self, line_number: usize) -> Result<BlamedLine> {
+        let mut blamed = self
+            .hunks
+            .iter()
+            .find(|hunk| hunk.contains(line_number))
+            .with_context(|| format!("No blame information found for line {}", line_number))?
+            .blame_line(line_number)?;
+
+        while self.config.ignore_revs.contains(&blamed.commit_id) && !blamed.boundary {
+            match self.blame_before(&blamed)? {
+                Some(earlier) => blamed = earlier,
+                None => break,
+            }
+        }
+        Ok(blamed)
+    }
+
+    /// Blames the line the way it was in the parent of the commit it was blamed on.
+    fn blame_before(&self, blamed: &BlamedLine) -> Result<Option<BlamedLine>> {
+        let commit = self.repo.find_commit(blamed.commit_id)?;
+        let (parent, path) = match (commit.parents().next(), &blamed.path) {
+            (Some(parent), Some(path)) => (parent, path),
+            _ => return Ok(None),
+        };
+        let (old, new) = match (parent.tree()?.get_path(path), commit.tree()?.get_path(path)) {
+            (Ok(old), Ok(new)) => (
+                self.repo.find_blob(old.id())?,
+                self.repo.find_blob(new.id())?,
+            ),
+            // The file was created by the commit
+            _ => return Ok(None),
+        };
+
+        let mut diff_options = DiffOptions::new();
+        diff_options
+            .context_lines(0)
+            .ignore_whitespace(self.config.ignore_whitespace);
+        let patch = Patch::from_blobs(&old, None, &new, None, Some(&mut diff_options))?;
+        let line = match line_before_patch(&patch, blamed.line)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        let mut options = self.config.options();
+        options.newest_commit(parent.id());
+        let blame = self.repo.blame_file(path, Some(&mut options))?;
+        blame
+            .get_line(line)
+            .map(|hunk| Hunk::new(&hunk).blame_line(line))
+            .transpose()
+    }
+}
+
+/// Where `line` of the new side of `patch` was on the old side. A changed line maps to the line
+/// at the same position in the hunk it replaced, or the hunk's last line if the hunk shrank.
+/// `None` if the line was added.
+fn line_before_patch(patch: &Patch, line: usize) -> Result<Option<usize>> {
+    let mut offset: isize = 0;
+    for index in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(index)?;
+        let (new_start, new_lines) = (hunk.new_start() as usize, hunk.new_lines() as usize);
+        let (old_start, old_lines) = (hunk.old_start() as usize, hunk.old_lines() as usize);
+        if line < new_start {
+            break;
+        }
+        if line < new_start + new_lines {
+            if old_lines == 0 {
+                return Ok(None);
+            }
+            return Ok(Some(old_start + (line - new_start).min(old_lines - 1)));
+        }
+        offset += old_lines as isize - new_lines as isize;
+    }
+    Ok(Some(line.saturating_add_signed(offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_blame_line_skips_ignored_revs() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        let original = test_repo::commit(&repo, &[("a.rs", "fn a() {\n// todo: a\n}\n")], "first");
+        let reformat = test_repo::commit(
+            &repo,
+            &[("a.rs", "\n\nfn a() {\n    // todo: a\n}\n// todo: b\n")],
+            "reformat",
+        );
+
+        let config = BlameConfig {
+            ignore_revs: HashSet::from([reformat]),
+            ..BlameConfig::default()
+        };
+        let context = FileBlameContext::new(&repo, Path::new("a.rs"), &config)?;
+
+        let blamed = context.blame_line(4)?;
+        assert_eq!(blamed.commit_id, original);
+        assert_eq!(blamed.line, 2);
+        // Added by the ignored commit, so there's no one else to blame
+        assert_eq!(context.blame_line(6)?.commit_id, reformat);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blame_line_of_buffer() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        let commit = test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "first");
+
+        let config = BlameConfig::default();
+        let context = FileBlameContext::for_working_tree(
+            &repo,
+            Path::new("a.rs"),
+            b"// todo: new\n// todo: a\n",
+            Utc::now(),
+            &config,
+            None,
+        )?;
+
+        assert!(context.blame_line(1)?.commit_id.is_zero());
+        let blamed = context.blame_line(2)?;
+        assert_eq!(blamed.commit_id, commit);
+        assert_eq!(blamed.line, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blame_line_of_untracked_file() -> Result<()> {
+        let config = BlameConfig::default();
+        let blame_new_file = |repo| {
+            let content = b"// todo: a\n// todo: b\n";
+            FileBlameContext::for_working_tree(
+                repo,
+                Path::new("new.rs"),
+                content,
+                Utc::now(),
+                &config,
+                None,
+            )?
+            .blame_line(2)
+        };
+
+        // Before the first commit, and once there is one without the file
+        let (_dir, repo) = test_repo::init();
+        assert!(blame_new_file(&repo)?.commit_id.is_zero());
+
+        test_repo::commit(&repo, &[("a.rs", "")], "first");
+        let blamed = blame_new_file(&repo)?;
+        assert!(blamed.commit_id.is_zero());
+        assert_eq!(blamed.line, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_ignore_revs() -> Result<()> {
+        let rev = "0123456789abcdef0123456789abcdef01234567";
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "# Reformatting\n{} # rustfmt\n\n", rev)?;
+        assert_eq!(
+            load_ignore_revs(file.path())?,
+            HashSet::from([Oid::from_str(rev)?])
+        );
+
+        writeln!(file, "0123456")?;
+        let err = load_ignore_revs(file.path()).unwrap_err();
+        assert!(err.to_string().contains("expected a full commit hash"));
+        Ok(())
+    }
+}
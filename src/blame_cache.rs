@@ -0,0 +1,276 @@
+use crate::{
+    blame_types::{BlameCacheFile, BlameConfig, CachedBlame, Hunk},
+    utils::paths_changed_since,
+};
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+const BLAME_CACHE_VERSION: u32 = 1;
+
+/// Reuses the blame of committed files across scans. A file's blame stays valid while HEAD moves
+/// forward through commits that don't touch the file; anything else is blamed again. Each repository, nested ones
+/// and submodules included, has its own cache in its git directory.
+pub struct BlameCache {
+    /// The blame options of every cached hunk, see `BlameConfig::cache_key`.
+    options: String,
+    /// Per git directory, loaded when a file of the repository is first looked up. `None` if
+    /// HEAD doesn't point at a commit yet.
+    repos: HashMap<PathBuf, Option<RepoBlameCache>>,
+}
+
+struct RepoBlameCache {
+    /// The cache file, in the git directory of the repository it belongs to.
+    path: PathBuf,
+    head: Oid,
+    file: BlameCacheFile,
+    /// Paths changed since each cached commit looked up so far, see `paths_changed_since`.
+    changed_paths: HashMap<String, Option<HashSet<PathBuf>>>,
+    changed: bool,
+}
+
+impl BlameCache {
+    /// Caches blames computed with `config`.
+    pub fn new(config: &BlameConfig) -> Self {
+        BlameCache {
+            options: config.cache_key(),
+            repos: HashMap::new(),
+        }
+    }
+
+    /// The cached hunks of `path`, relative to the root of `repo`, if they were computed for
+    /// `blob`, its content at HEAD, at a commit HEAD descends from without touching `path`.
+    pub fn get(&mut self, repo: &Repository, path: &Path, blob: Oid) -> Option<Vec<Hunk>> {
+        let cache = self.repo_cache(repo)?;
+        let cached = cache.file.files.get(path)?;
+        if cached.blob != blob.to_string() {
+            return None;
+        }
+
+        let head = cache.head;
+        if cached.commit != head.to_string() {
+            let changed_paths = cache
+                .changed_paths
+                .entry(cached.commit.clone())
+                .or_insert_with(|| {
+                    let commit = Oid::from_str(&cached.commit).ok()?;
+                    paths_changed_since(repo, commit, head).ok().flatten()
+                });
+            if changed_paths
+                .as_ref()
+                .is_none_or(|changed_paths| changed_paths.contains(path))
+            {
+                return None;
+            }
+            // Saves walking the commits next time
+            let cached = cache.file.files.get_mut(path)?;
+            cached.commit = head.to_string();
+            cache.changed = true;
+        }
+        cache
+            .file
+            .files
+            .get(path)
+            .map(|cached| cached.hunks.clone())
+    }
+
+    /// Caches the hunks of `path` blamed at HEAD of `repo`, where its content is `blob`.
+    pub fn insert(&mut self, repo: &Repository, path: &Path, blob: Oid, hunks: Vec<Hunk>) {
+        if let Some(cache) = self.repo_cache(repo) {
+            cache.file.files.insert(
+                path.to_path_buf(),
+                CachedBlame {
+                    commit: cache.head.to_string(),
+                    blob: blob.to_string(),
+                    hunks,
+                },
+            );
+            cache.changed = true;
+        }
+    }
+
+    /// Writes the cache of every repository where anything changed, leaving out files that are
+    /// gone from HEAD.
+    pub fn save(&mut self) -> Result<()> {
+        for (git_dir, cache) in &mut self.repos {
+            if let Some(cache) = cache.as_mut().filter(|cache| cache.changed) {
+                let repo = Repository::open(git_dir)?;
+                cache.save(&repo)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn repo_cache(&mut self, repo: &Repository) -> Option<&mut RepoBlameCache> {
+        let options = &self.options;
+        self.repos
+            .entry(repo.path().to_path_buf())
+            .or_insert_with(|| RepoBlameCache::load(repo, options))
+            .as_mut()
+    }
+}
+
+impl RepoBlameCache {
+    fn load(repo: &Repository, options: &str) -> Option<Self> {
+        let head = repo.head().and_then(|head| head.peel_to_commit()).ok()?;
+        let path = repo.path().join("todolint").join("blame-cache.json");
+
+        // The cache is only an optimization, so an unreadable or outdated one is started over
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<BlameCacheFile>(&content).ok())
+            .filter(|file| file.version == BLAME_CACHE_VERSION && file.options == options)
+            .unwrap_or_else(|| BlameCacheFile {
+                version: BLAME_CACHE_VERSION,
+                options: options.to_string(),
+                files: BTreeMap::new(),
+            });
+
+        Some(RepoBlameCache {
+            path,
+            head: head.id(),
+            file,
+            changed_paths: HashMap::new(),
+            changed: false,
+        })
+    }
+
+    fn save(&mut self, repo: &Repository) -> Result<()> {
+        let tree = repo.find_commit(self.head)?.tree()?;
+        self.file
+            .files
+            .retain(|path, _| tree.get_path(path).is_ok());
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec(&self.file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+
+    fn hunks(path: &str) -> Vec<Hunk> {
+        vec![Hunk {
+            commit_id: Oid::zero().to_string(),
+            path: Some(PathBuf::from(path)),
+            start_line: 1,
+            lines: 1,
+            orig_start_line: 1,
+            boundary: false,
+        }]
+    }
+
+    fn blob(repo: &Repository, path: &str) -> Result<Oid> {
+        Ok(repo.head()?.peel_to_tree()?.get_path(Path::new(path))?.id())
+    }
+
+    #[test]
+    fn test_blame_cache_survives_unrelated_commits() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n"), ("b.rs", "")], "first");
+        let config = BlameConfig::default();
+
+        let mut cache = BlameCache::new(&config);
+        cache.insert(
+            &repo,
+            Path::new("a.rs"),
+            blob(&repo, "a.rs")?,
+            hunks("a.rs"),
+        );
+        cache.save()?;
+
+        // Moving HEAD without touching the file keeps its blame
+        test_repo::commit(&repo, &[("b.rs", "// todo: b\n")], "second");
+        let mut cache = BlameCache::new(&config);
+        assert_eq!(
+            cache.get(&repo, Path::new("a.rs"), blob(&repo, "a.rs")?),
+            Some(hunks("a.rs"))
+        );
+        cache.save()?;
+
+        // Changing the blame options or the file doesn't
+        let first_parent = BlameConfig {
+            first_parent: true,
+            ..BlameConfig::default()
+        };
+        let mut cache = BlameCache::new(&first_parent);
+        assert_eq!(
+            cache.get(&repo, Path::new("a.rs"), blob(&repo, "a.rs")?),
+            None
+        );
+        test_repo::commit(&repo, &[("a.rs", "// todo: c\n")], "third");
+        let mut cache = BlameCache::new(&config);
+        assert_eq!(
+            cache.get(&repo, Path::new("a.rs"), blob(&repo, "a.rs")?),
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_blame_cache_invalidated_by_reverted_change() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "first");
+        let config = BlameConfig::default();
+        let committed = blob(&repo, "a.rs")?;
+
+        let mut cache = BlameCache::new(&config);
+        cache.insert(&repo, Path::new("a.rs"), committed, hunks("a.rs"));
+        cache.save()?;
+
+        // The content is back to the cached blob, but its lines were last changed by the revert
+        test_repo::commit(&repo, &[("a.rs", "// todo: b\n")], "change");
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "revert");
+        assert_eq!(blob(&repo, "a.rs")?, committed);
+        let mut cache = BlameCache::new(&config);
+        assert_eq!(cache.get(&repo, Path::new("a.rs"), committed), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blame_cache_per_repository() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "first");
+        let inner = Repository::init(dir.path().join("vendor/inner"))?;
+        test_repo::commit(&inner, &[("a.rs", "// todo: inner\n")], "first");
+        let config = BlameConfig::default();
+
+        let mut cache = BlameCache::new(&config);
+        cache.insert(
+            &repo,
+            Path::new("a.rs"),
+            blob(&repo, "a.rs")?,
+            hunks("a.rs"),
+        );
+        cache.insert(
+            &inner,
+            Path::new("a.rs"),
+            blob(&inner, "a.rs")?,
+            hunks("inner"),
+        );
+        cache.save()?;
+
+        assert!(inner.path().join("todolint/blame-cache.json").is_file());
+        let mut cache = BlameCache::new(&config);
+        assert_eq!(
+            cache.get(&inner, Path::new("a.rs"), blob(&inner, "a.rs")?),
+            Some(hunks("inner"))
+        );
+        assert_eq!(
+            cache.get(&repo, Path::new("a.rs"), blob(&repo, "a.rs")?),
+            Some(hunks("a.rs"))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+/// How todos are blamed. The default blames the same way as plain `git blame`.
+#[derive(Default, Debug)]
+pub struct BlameConfig {
+    /// Commits whose changes are blamed on the commits before them, e.g. mass reformatting.
+    pub ignore_revs: HashSet<Oid>,
+    /// Follow lines moved or copied between files.
+    pub track_copies: bool,
+    /// Only follow the first parent of merge commits.
+    pub first_parent: bool,
+    pub ignore_whitespace: bool,
+    /// Lines last changed before this commit are blamed on it.
+    pub oldest_commit: Option<Oid>,
+}
+
+/// The commit a line was last changed in, and where the line was in that commit.
+#[derive(Debug, PartialEq)]
+pub struct BlamedLine {
+    pub commit_id: Oid,
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    /// Whether the blame stopped at the oldest commit rather than where the line was changed.
+    pub boundary: bool,
+}
+
+/// Consecutive lines blamed on the same commit. Unlike `git2::BlameHunk`, it doesn't borrow the
+/// blame it came from, so it can be cached.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub commit_id: String,
+    /// Path of the file in the commit.
+    pub path: Option<PathBuf>,
+    /// First line of the hunk in the blamed file, 1-based.
+    pub start_line: usize,
+    pub lines: usize,
+    /// First line of the hunk in the commit.
+    pub orig_start_line: usize,
+    pub boundary: bool,
+}
+
+/// Blame of committed files, kept between scans in the repository's git directory.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BlameCacheFile {
+    pub version: u32,
+    /// The blame options the hunks were computed with, see `BlameConfig::cache_key`.
+    pub options: String,
+    /// Per path relative to the repository root.
+    pub files: BTreeMap<PathBuf, CachedBlame>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedBlame {
+    /// HEAD when the file was last blamed or found unchanged.
+    pub commit: String,
+    /// The blamed content, which is the same at every commit since `commit` was blamed.
+    pub blob: String,
+    pub hunks: Vec<Hunk>,
+}
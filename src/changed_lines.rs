@@ -0,0 +1,212 @@
+use crate::path_analyzer_types::{AnalysisResult, FileAnalysis};
+use anyhow::{Context, Result};
+use git2::{DiffOptions, ObjectType, Repository, Tree};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Lines added or modified since the merge base of HEAD and a base ref, including uncommitted
+/// changes and untracked files. Used to only report todos that a branch introduces. Submodules are
+/// compared against the commit the merge base records for them.
+pub struct ChangedLines {
+    /// Per repository, the canonical root of its working tree and the 1-based line numbers per
+    /// path relative to it. Submodules come after the repository containing them.
+    repos: Vec<(PathBuf, HashMap<PathBuf, HashSet<usize>>)>,
+}
+
+impl ChangedLines {
+    pub fn new(repo: &Repository, base: &str) -> Result<Self> {
+        let base_commit = repo
+            .revparse_single(base)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Failed to resolve base ref '{}'", base))?;
+        let head_commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("Failed to resolve HEAD")?;
+        let merge_base = repo
+            .merge_base(base_commit.id(), head_commit.id())
+            .with_context(|| format!("HEAD has no common ancestor with '{}'", base))?;
+        let merge_base_tree = repo.find_commit(merge_base)?.tree()?;
+
+        let mut changed_lines = ChangedLines { repos: Vec::new() };
+        changed_lines.add_repo(repo, Some(&merge_base_tree))?;
+        Ok(changed_lines)
+    }
+
+    /// Adds the lines of `repo` changed since `base_tree`, or all of them without one, and then
+    /// those of its submodules.
+    fn add_repo(&mut self, repo: &Repository, base_tree: Option<&Tree>) -> Result<()> {
+        let workdir = repo
+            .workdir()
+            .context("Can't compare against a base ref in a bare repository")?;
+
+        let mut diff_opts = DiffOptions::new();
+        diff_opts
+            .context_lines(0)
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = repo
+            .diff_tree_to_workdir_with_index(base_tree, Some(&mut diff_opts))
+            .context("Failed to diff against the merge base")?;
+
+        let mut lines: HashMap<PathBuf, HashSet<usize>> = HashMap::new();
+        diff.foreach(
+            &mut |_, _| true,
+            None,
+            None,
+            Some(&mut |delta, _, line| {
+                if let (Some(path), Some(line_number)) =
+                    (delta.new_file().path(), line.new_lineno())
+                {
+                    if line.origin() == '+' {
+                        lines
+                            .entry(path.to_path_buf())
+                            .or_default()
+                            .insert(line_number as usize);
+                    }
+                }
+                true
+            }),
+        )?;
+        self.repos.push((fs::canonicalize(workdir)?, lines));
+
+        // A submodule added since the base is new altogether. One that isn't checked out has
+        // nothing to report, and one missing the recorded commit can't be compared
+        let index = repo.index().context("Failed to read the index")?;
+        for entry in index.iter() {
+            if entry.mode & 0o170000 != 0o160000 {
+                continue;
+            }
+            let path = PathBuf::from(String::from_utf8_lossy(&entry.path).as_ref());
+            let submodule = match Repository::open(workdir.join(&path)) {
+                Ok(submodule) => submodule,
+                Err(_) => continue,
+            };
+            let recorded = base_tree
+                .and_then(|tree| tree.get_path(&path).ok())
+                .filter(|entry| entry.kind() == Some(ObjectType::Commit));
+            let submodule_base_tree = match recorded {
+                Some(recorded) => match submodule
+                    .find_commit(recorded.id())
+                    .and_then(|commit| commit.tree())
+                {
+                    Ok(tree) => Some(tree),
+                    Err(_) => continue,
+                },
+                None => None,
+            };
+            self.add_repo(&submodule, submodule_base_tree.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Drops every todo that isn't on a changed line.
+    pub fn restrict(&self, analysis: &mut AnalysisResult) {
+        for file_analysis in analysis.file_analyses_mut() {
+            self.restrict_file(file_analysis);
+        }
+    }
+
+    pub fn restrict_file(&self, file_analysis: &mut FileAnalysis) {
+        let changed = self.lines(&file_analysis.metadata.filepath);
+        let is_changed = |line: usize| changed.is_some_and(|changed| changed.contains(&line));
+
+        if let Some(valids) = file_analysis.valids.as_mut() {
+            valids.retain(|valid| is_changed(valid.line));
+            if valids.is_empty() {
+                file_analysis.valids = None;
+            }
+        }
+        if let Some(invalids) = file_analysis.invalids.as_mut() {
+            invalids.retain(|invalid| is_changed(invalid.line));
+            if invalids.is_empty() {
+                file_analysis.invalids = None;
+            }
+        }
+    }
+
+    /// Changed lines of the file at `filepath`, in the innermost repository it's in.
+    fn lines(&self, filepath: &Path) -> Option<&HashSet<usize>> {
+        let path = fs::canonicalize(filepath).ok()?;
+        self.repos.iter().rev().find_map(|(workdir, lines)| {
+            path.strip_prefix(workdir).ok().map(|path| lines.get(path))
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+
+    fn lines_of(changed_lines: &ChangedLines, path: &Path) -> Vec<usize> {
+        let mut lines: Vec<usize> = changed_lines
+            .lines(path)
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_changed_lines_since_merge_base() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "one\ntwo\nthree\n")], "base");
+        let base = repo.head()?.peel_to_commit()?;
+        repo.branch("base", &base, false)?;
+        test_repo::commit(&repo, &[("a.rs", "one\n2\nthree\nfour\n")], "branch");
+        fs::write(dir.path().join("a.rs"), "zero\none\n2\nthree\nfour\n")?;
+        fs::write(dir.path().join("new.rs"), "x\ny\n")?;
+
+        let changed_lines = ChangedLines::new(&repo, "base")?;
+
+        assert_eq!(
+            lines_of(&changed_lines, &dir.path().join("a.rs")),
+            vec![1, 3, 5]
+        );
+        assert_eq!(
+            lines_of(&changed_lines, &dir.path().join("new.rs")),
+            vec![1, 2]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_lines_of_submodules() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        let lib = Repository::init(dir.path().join("lib"))?;
+        test_repo::commit(&lib, &[("x.rs", "one\n")], "first");
+        let mut index = repo.index()?;
+        index.add_path(Path::new("lib"))?;
+        index.write()?;
+        test_repo::commit(&repo, &[("a.rs", "")], "base");
+        let base = repo.head()?.peel_to_commit()?;
+        repo.branch("base", &base, false)?;
+
+        // Changed since the commit the base records, and added since the base
+        test_repo::commit(&lib, &[("x.rs", "one\ntwo\n")], "second");
+        let added = Repository::init(dir.path().join("added"))?;
+        test_repo::commit(&added, &[("y.rs", "a\n")], "first");
+        index.add_path(Path::new("lib"))?;
+        index.add_path(Path::new("added"))?;
+        index.write()?;
+
+        let changed_lines = ChangedLines::new(&repo, "base")?;
+
+        assert_eq!(
+            lines_of(&changed_lines, &dir.path().join("lib/x.rs")),
+            vec![2]
+        );
+        assert_eq!(
+            lines_of(&changed_lines, &dir.path().join("added/y.rs")),
+            vec![1]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{
+    baseline::DEFAULT_BASELINE_PATH,
+    ratchet::DEFAULT_RATCHET_PATH,
+    ratchet_types::RatchetGroup,
+    reporters::{Column, OutputFormat},
+    trend_types::{TrendFormat, TrendSampling},
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{env, path::PathBuf};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scan a file or directory for todo comments.
+    Scan(ScanArgs),
+    /// Render a previously saved JSON or BSON report in another format.
+    Convert(ConvertArgs),
+    /// Print the JSON Schema of the JSON report.
+    Schema,
+    /// Compare two scans, given as report files or git revisions.
+    Diff(DiffArgs),
+    /// Manage the baseline of grandfathered invalid todos.
+    Baseline(BaselineArgs),
+    /// Scan and fail if there are invalid todos that aren't in the baseline.
+    Check(CheckArgs),
+    /// Manage git hooks.
+    Hook(HookArgs),
+    /// Show when each todo was created, last modified and resolved, from the git history.
+    History(HistoryArgs),
+    /// Chart valid and invalid todo counts over the git history.
+    Trend(TrendArgs),
+}
+
+/// What to scan.
+#[derive(Args)]
+pub struct SourceArgs {
+    /// File or directory to scan.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+    /// Only report todos on lines added or modified since the merge base of HEAD and this ref,
+    /// including uncommitted changes, e.g. `origin/main`.
+    #[arg(long, value_name = "REF")]
+    pub base: Option<String>,
+    /// Scan the content staged for commit instead of the working tree, e.g. in a pre-commit
+    /// hook. Only staged files are reported.
+    #[arg(long, conflicts_with = "base")]
+    pub staged: bool,
+    /// Scan the tree of a commit, tag or branch instead of the working tree, without checking it
+    /// out. Todos are blamed as of that commit.
+    #[arg(long, value_name = "REV", conflicts_with_all = ["base", "staged"])]
+    pub rev: Option<String>,
+    /// Don't scan submodules, or other git repositories nested in the scanned directory. Scans of
+    /// the index or a commit never include them.
+    #[arg(long)]
+    pub skip_submodules: bool,
+    /// Where to keep the analysis of each scanned file, so later scans only analyze files
+    /// modified since. Defaults to the git directory of the scanned repository; outside a
+    /// repository, nothing is cached unless this is given.
+    #[arg(long, value_name = "FILE")]
+    pub scan_cache: Option<PathBuf>,
+    /// Analyze every file from scratch, without reusing or saving earlier results.
+    #[arg(long, conflicts_with = "scan_cache")]
+    pub no_scan_cache: bool,
+    #[command(flatten)]
+    pub blame: BlameArgs,
+}
+
+impl SourceArgs {
+    /// Whether content is read from git rather than the working tree.
+    pub fn reads_from_git(&self) -> bool {
+        self.staged || self.rev.is_some()
+    }
+}
+
+/// How todos are blamed.
+#[derive(Args, Default)]
+pub struct BlameArgs {
+    /// Blame the lines a commit changed on the commit before it, e.g. for mass reformatting. Can
+    /// be given multiple times.
+    #[arg(long = "ignore-rev", value_name = "REV")]
+    pub ignore_revs: Vec<String>,
+    /// File of commits to ignore like `--ignore-rev`, one full hash per line. Defaults to git's
+    /// `blame.ignoreRevsFile` setting, or `.git-blame-ignore-revs` at the repository root.
+    #[arg(long, value_name = "FILE")]
+    pub ignore_revs_file: Option<PathBuf>,
+    /// Don't read commits to ignore from any file.
+    #[arg(long, conflicts_with = "ignore_revs_file")]
+    pub no_ignore_revs_file: bool,
+    /// Follow lines moved or copied from other files, where libgit2 supports it.
+    #[arg(long)]
+    pub blame_copies: bool,
+    /// Only follow the first parent of merge commits, so lines merged from a branch are blamed
+    /// on the merge.
+    #[arg(long)]
+    pub blame_first_parent: bool,
+    /// Ignore whitespace changes when blaming.
+    #[arg(long)]
+    pub blame_ignore_whitespace: bool,
+    /// Blame lines last changed before this commit on it instead of digging further back.
+    #[arg(long, value_name = "REV")]
+    pub blame_since: Option<String>,
+    /// Don't reuse or save the blame of unmodified files, cached in the git directory.
+    #[arg(long)]
+    pub no_blame_cache: bool,
+}
+
+#[derive(Args)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Report to read.
+    pub input: PathBuf,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Report file or git revision of the old scan.
+    pub old: String,
+    /// Report file or git revision of the new scan.
+    pub new: String,
+    #[arg(long, value_enum, default_value_t = DiffFormat::Terminal)]
+    pub format: DiffFormat,
+}
+
+#[derive(Args)]
+pub struct BaselineArgs {
+    #[command(subcommand)]
+    pub command: BaselineCommand,
+}
+
+#[derive(Subcommand)]
+pub enum BaselineCommand {
+    /// Write every current invalid todo to the baseline, replacing its previous content.
+    Create(BaselineCreateArgs),
+    /// Remove entries for todos that no longer exist, without adding any.
+    Prune(BaselinePruneArgs),
+}
+
+#[derive(Args)]
+pub struct BaselineCreateArgs {
+    /// File or directory to scan.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+    /// Baseline file to write.
+    #[arg(long, default_value = DEFAULT_BASELINE_PATH)]
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct BaselinePruneArgs {
+    /// File or directory to scan. Entries outside of it are kept.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+    /// Baseline file to prune.
+    #[arg(long, default_value = DEFAULT_BASELINE_PATH)]
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+    /// Baseline file. Defaults to `.todolint-baseline.json` if it exists.
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+    /// Also fail when baselined todos no longer exist.
+    #[arg(long)]
+    pub fail_on_stale: bool,
+    /// Instead of failing on any invalid todo, fail when a todo count in the ratchet file
+    /// increases. Counts that drop are lowered in the file. Defaults to `.todolint-ratchet.json`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = DEFAULT_RATCHET_PATH
+    )]
+    pub ratchet: Option<PathBuf>,
+    /// How the ratchet groups todo counts. Only needed when creating the ratchet file.
+    #[arg(long, value_enum)]
+    pub ratchet_by: Option<RatchetGroup>,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub command: HookCommand,
+}
+
+#[derive(Subcommand)]
+pub enum HookCommand {
+    /// Install a pre-commit hook that runs `todolint check --staged`.
+    Install(HookInstallArgs),
+}
+
+#[derive(Args)]
+pub struct HookInstallArgs {
+    /// Replace an existing pre-commit hook that wasn't installed by todolint.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Commit whose first-parent history is walked.
+    #[arg(default_value = "HEAD")]
+    pub rev: String,
+    #[arg(long, value_enum, default_value_t = HistoryFormat::Terminal)]
+    pub format: HistoryFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum HistoryFormat {
+    Terminal,
+    Json,
+}
+
+#[derive(Args)]
+pub struct TrendArgs {
+    /// Commit whose first-parent history is sampled.
+    #[arg(default_value = "HEAD")]
+    pub rev: String,
+    #[arg(long, value_enum, default_value_t = TrendSampling::Commit)]
+    pub sample: TrendSampling,
+    /// Sample every Nth commit when sampling commits.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub every: u64,
+    #[arg(long, value_enum, default_value_t = TrendFormat::Csv)]
+    pub format: TrendFormat,
+    /// Write the trend to this file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DiffFormat {
+    Terminal,
+    Json,
+}
+
+/// How and where to write a report.
+#[derive(Args)]
+pub struct ReportArgs {
+    #[arg(long, value_enum, default_value_t = OutputFormat::Terminal)]
+    pub format: OutputFormat,
+    /// Write the report to this file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Comma separated columns for tabular formats. Defaults to all columns.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Option<Vec<Column>>,
+    /// When to use colors and hyperlinks in terminal output.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal && env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
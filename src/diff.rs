@@ -0,0 +1,352 @@
+use crate::{
+    diff_types::{DiffTodo, ModifiedTodo, MovedTodo, ScanDiff, TodoStatus},
+    line_analyzer_types::CommentMarker,
+    path_analyzer_types::AnalysisResult,
+};
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::PathBuf,
+};
+
+/// Every todo of an analysis along with its fingerprint. Paths are relative to the repository
+/// root, or normalized outside of one, so that reports scanned as `./src`, `src` or from another
+/// directory line up.
+pub fn fingerprinted_todos(analysis: &AnalysisResult) -> Vec<DiffTodo> {
+    let mut todos = Vec::new();
+
+    for file_analysis in analysis.file_analyses() {
+        let path = file_analysis.metadata.stable_path();
+
+        for valid in file_analysis.valids.iter().flatten() {
+            todos.push(DiffTodo {
+                line: valid.line,
+                status: TodoStatus::Valid,
+                text: format!(
+                    "{}{}: {}",
+                    CommentMarker::Todo,
+                    valid.line_info.delimiters_text(),
+                    valid.line_info.comment
+                ),
+                fingerprint: valid.fingerprint(&path),
+                path: path.clone(),
+            });
+        }
+        for invalid in file_analysis.invalids.iter().flatten() {
+            todos.push(DiffTodo {
+                line: invalid.line,
+                status: TodoStatus::Invalid,
+                text: invalid.line_info.full_text.clone(),
+                fingerprint: invalid.fingerprint(&path),
+                path: path.clone(),
+            });
+        }
+    }
+
+    todos
+}
+
+/// Compares two scans. Todos with the same fingerprint are paired up in line order. A pair that
+/// kept its place relative to the other pairs of its file is unchanged, even if lines above it
+/// were added or removed; one that changed places with them is moved. Of the rest, an old and a
+/// new todo at corresponding lines (adjusted by the shift of the nearest unchanged pair above)
+/// are a modification; everything else was added or removed.
+pub fn diff_scans(old: &AnalysisResult, new: &AnalysisResult) -> ScanDiff {
+    let mut old_by_fingerprint = group_by_fingerprint(fingerprinted_todos(old));
+    let new_by_fingerprint = group_by_fingerprint(fingerprinted_todos(new));
+
+    let mut diff = ScanDiff::default();
+    let mut unmatched_new = Vec::new();
+    // Old line and new todo of every matched pair, per file
+    let mut pairs: HashMap<PathBuf, Vec<(usize, DiffTodo)>> = HashMap::new();
+
+    for (fingerprint, new_todos) in new_by_fingerprint {
+        let mut old_todos = old_by_fingerprint.remove(&fingerprint).unwrap_or_default();
+
+        // Prefer todos that stayed on the same line, then pair up the rest in order
+        let mut remaining_new = Vec::new();
+        for new_todo in new_todos {
+            match old_todos.iter().position(|old| old.line == new_todo.line) {
+                Some(index) => {
+                    old_todos.remove(index);
+                    pairs
+                        .entry(new_todo.path.clone())
+                        .or_default()
+                        .push((new_todo.line, new_todo));
+                }
+                None => remaining_new.push(new_todo),
+            }
+        }
+
+        let mut old_todos = old_todos.into_iter();
+        for new_todo in remaining_new {
+            match old_todos.next() {
+                Some(old_todo) => pairs
+                    .entry(new_todo.path.clone())
+                    .or_default()
+                    .push((old_todo.line, new_todo)),
+                None => unmatched_new.push(new_todo),
+            }
+        }
+        diff.removed.extend(old_todos);
+    }
+    diff.removed
+        .extend(old_by_fingerprint.into_values().flatten());
+
+    // (old line, new line) of every unchanged pair, per file
+    let mut anchors: HashMap<PathBuf, Vec<(usize, usize)>> = HashMap::new();
+    for (path, mut file_pairs) in pairs {
+        file_pairs.sort_by_key(|(old_line, _)| *old_line);
+        let lines: Vec<(usize, usize)> = file_pairs
+            .iter()
+            .map(|(old_line, new_todo)| (*old_line, new_todo.line))
+            .collect();
+        let in_order = in_order_pairs(&lines);
+
+        let file_anchors = anchors.entry(path).or_default();
+        for ((old_line, new_todo), in_order) in file_pairs.into_iter().zip(in_order) {
+            let moved = MovedTodo {
+                from_line: old_line,
+                todo: new_todo,
+            };
+            if !in_order {
+                diff.moved.push(moved);
+                continue;
+            }
+            file_anchors.push((old_line, moved.todo.line));
+            diff.summary.unchanged += 1;
+            if old_line != moved.todo.line {
+                diff.shifted.push(moved);
+            }
+        }
+    }
+
+    // Pair leftovers that sit where the other would be expected
+    let mut removed = Vec::new();
+    for old_todo in std::mem::take(&mut diff.removed) {
+        let shift = anchors
+            .get(&old_todo.path)
+            .and_then(|file_anchors| {
+                file_anchors
+                    .iter()
+                    .rev()
+                    .find(|(old_line, _)| *old_line < old_todo.line)
+            })
+            .map_or(0, |(old_line, new_line)| {
+                *new_line as isize - *old_line as isize
+            });
+        let expected_line = old_todo.line as isize + shift;
+
+        match unmatched_new.iter().position(|new_todo| {
+            new_todo.path == old_todo.path && new_todo.line as isize == expected_line
+        }) {
+            Some(index) => diff.modified.push(ModifiedTodo {
+                old: old_todo,
+                new: unmatched_new.remove(index),
+            }),
+            None => removed.push(old_todo),
+        }
+    }
+    diff.removed = removed;
+    diff.added = unmatched_new;
+
+    let by_location = |todo: &DiffTodo| (todo.path.clone(), todo.line);
+    diff.added.sort_by_key(by_location);
+    diff.removed.sort_by_key(by_location);
+    diff.moved.sort_by_key(|moved| by_location(&moved.todo));
+    diff.modified
+        .sort_by_key(|modified| by_location(&modified.new));
+
+    diff.summary.added = diff.added.len();
+    diff.summary.removed = diff.removed.len();
+    diff.summary.moved = diff.moved.len();
+    diff.summary.modified = diff.modified.len();
+    diff
+}
+
+/// Which of the (old line, new line) `pairs`, sorted by old line, keep their order relative to
+/// each other: the largest set of pairs whose new lines are in the same order as their old ones,
+/// preferring pairs that stayed on the same line. The other pairs changed places.
+fn in_order_pairs(pairs: &[(usize, usize)]) -> Vec<bool> {
+    // Best (pairs, same-line pairs) of an ordered set ending at each pair, and the pair before it
+    let mut best: Vec<(usize, usize)> = Vec::with_capacity(pairs.len());
+    let mut previous: Vec<Option<usize>> = Vec::with_capacity(pairs.len());
+    for (index, &(old_line, new_line)) in pairs.iter().enumerate() {
+        let weight = (1, usize::from(old_line == new_line));
+        let mut current = (weight, None);
+        for before in 0..index {
+            let (before_old, before_new) = pairs[before];
+            let candidate = (best[before].0 + weight.0, best[before].1 + weight.1);
+            if before_old < old_line && before_new < new_line && candidate > current.0 {
+                current = (candidate, Some(before));
+            }
+        }
+        best.push(current.0);
+        previous.push(current.1);
+    }
+
+    let mut in_order = vec![false; pairs.len()];
+    let mut current = (0..pairs.len()).max_by_key(|&index| best[index]);
+    while let Some(index) = current {
+        in_order[index] = true;
+        current = previous[index];
+    }
+    in_order
+}
+
+/// Todos per fingerprint, in line order.
+fn group_by_fingerprint(todos: Vec<DiffTodo>) -> BTreeMap<String, Vec<DiffTodo>> {
+    let mut groups: BTreeMap<String, Vec<DiffTodo>> = BTreeMap::new();
+    for todo in todos {
+        groups
+            .entry(todo.fingerprint.clone())
+            .or_default()
+            .push(todo);
+    }
+    for todos in groups.values_mut() {
+        todos.sort_by_key(|todo| todo.line);
+    }
+    groups
+}
+
+/// One line per change, prefixed like a unified diff, followed by the summary.
+pub fn write_diff(diff: &ScanDiff, writer: &mut dyn Write) -> Result<()> {
+    for todo in &diff.added {
+        writeln!(
+            writer,
+            "+ {}:{}  {}",
+            todo.path.display(),
+            todo.line,
+            todo.text
+        )?;
+    }
+    for todo in &diff.removed {
+        writeln!(
+            writer,
+            "- {}:{}  {}",
+            todo.path.display(),
+            todo.line,
+            todo.text
+        )?;
+    }
+    for moved in &diff.moved {
+        writeln!(
+            writer,
+            "> {}:{} -> {}  {}",
+            moved.todo.path.display(),
+            moved.from_line,
+            moved.todo.line,
+            moved.todo.text
+        )?;
+    }
+    for modified in &diff.modified {
+        writeln!(
+            writer,
+            "~ {}:{}  {}  =>  {}",
+            modified.new.path.display(),
+            modified.new.line,
+            modified.old.text,
+            modified.new.text
+        )?;
+    }
+
+    let summary = &diff.summary;
+    writeln!(
+        writer,
+        "\n{} added, {} removed, {} moved, {} modified, {} unchanged",
+        summary.added, summary.removed, summary.moved, summary.modified, summary.unchanged
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_lines,
+        diff_types::DiffSummary,
+        path_analyzer_types::{DirectoryAnalysis, FileMetadata},
+    };
+    use chrono::Utc;
+
+    fn analysis_of(content: &str) -> AnalysisResult {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from("./src/lib.rs"),
+            repo_path: None,
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        let file_analysis = analyze_lines(metadata, content.as_bytes(), None).unwrap();
+
+        AnalysisResult::Directory(DirectoryAnalysis {
+            total_files_scanned: 1,
+            last_scan_on: Utc::now(),
+            file_analyses: vec![file_analysis],
+        })
+    }
+
+    #[test]
+    fn test_diff_scans() {
+        let old = analysis_of("// todo: a\n// todo: b\n// todo: c\n// todo: gone\n");
+        let new = analysis_of("// todo: new\n// todo: a\n// todo: b\n// todo: c2\n");
+
+        let diff = diff_scans(&old, &new);
+
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                moved: 0,
+                modified: 1,
+                unchanged: 2,
+            }
+        );
+        assert_eq!(diff.added[0].text, "todo: new");
+        assert_eq!(diff.removed[0].text, "todo: gone");
+        assert_eq!(
+            (
+                &diff.modified[0].old.text[..],
+                &diff.modified[0].new.text[..]
+            ),
+            ("todo: c", "todo: c2")
+        );
+        assert_eq!(diff.added[0].path, PathBuf::from("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_diff_scans_line_inserted_above() {
+        let old = analysis_of("// todo: a\n// todo: b\n// todo: c\n");
+        let new = analysis_of("\n// todo: c\n// todo: a\n\n// todo: b\n");
+
+        let diff = diff_scans(&old, &new);
+
+        // a and b only shifted, c moved above them
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                moved: 1,
+                unchanged: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(diff.moved[0].todo.text, "todo: c");
+        assert_eq!((diff.moved[0].from_line, diff.moved[0].todo.line), (3, 2));
+    }
+
+    #[test]
+    fn test_diff_identical_scans() {
+        let content = "// todo: a\n// todo: a\n// todo(x): b\n";
+        let diff = diff_scans(&analysis_of(content), &analysis_of(content));
+
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                unchanged: 3,
+                ..Default::default()
+            }
+        );
+    }
+}
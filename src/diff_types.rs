@@ -0,0 +1,59 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Changes between two scans. Todos are matched by content fingerprint, so a todo that only
+/// changed lines is moved rather than removed and added again.
+#[derive(Serialize, Debug, Default)]
+pub struct ScanDiff {
+    pub summary: DiffSummary,
+    pub added: Vec<DiffTodo>,
+    pub removed: Vec<DiffTodo>,
+    pub moved: Vec<MovedTodo>,
+    pub modified: Vec<ModifiedTodo>,
+    /// Unchanged todos on another line, because lines above them were added or removed. Not
+    /// reported, but needed to follow todos across scans.
+    #[serde(skip)]
+    pub shifted: Vec<MovedTodo>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub moved: usize,
+    pub modified: usize,
+    pub unchanged: usize,
+}
+
+/// A todo from either side of a diff.
+#[derive(Serialize, Debug, Clone)]
+pub struct DiffTodo {
+    pub path: PathBuf,
+    pub line: usize,
+    pub status: TodoStatus,
+    /// Normalized to `todo(...): comment` for valid todos, the matched text for invalid ones.
+    pub text: String,
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Valid,
+    Invalid,
+}
+
+/// Same todo on another line of the same file.
+#[derive(Serialize, Debug)]
+pub struct MovedTodo {
+    pub from_line: usize,
+    #[serde(flatten)]
+    pub todo: DiffTodo,
+}
+
+/// Todo whose content changed in place.
+#[derive(Serialize, Debug)]
+pub struct ModifiedTodo {
+    pub old: DiffTodo,
+    pub new: DiffTodo,
+}
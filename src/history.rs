@@ -0,0 +1,304 @@
+use crate::{
+    analyze_lines,
+    diff::diff_scans,
+    history_types::{CommitRef, TodoHistory},
+    line_analyzer_types::Email,
+    path_analyzer_types::{AnalysisResult, FileAnalysis, FileMetadata},
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{Commit, Delta, Repository, Sort, Tree};
+use std::{collections::HashMap, io::Write, path::PathBuf};
+
+impl CommitRef {
+    fn new(commit: &Commit) -> Result<Self> {
+        Ok(Self {
+            commit: commit.id().to_string(),
+            author: Email::new(commit.author().email()).to_string(),
+            timestamp: commit_time(commit)?,
+        })
+    }
+}
+
+pub fn commit_time(commit: &Commit) -> Result<DateTime<Utc>> {
+    Utc.timestamp_opt(commit.time().seconds(), 0)
+        .single()
+        .with_context(|| format!("Invalid timestamp: {}", commit.time().seconds()))
+}
+
+/// Walks the first-parent history up to `rev`, oldest first, without checking anything out.
+/// `on_commit` is called with each commit and the analyses of the files it changed; deleted
+/// files, submodules, binary and non UTF-8 files come without todos, same as when scanning.
+pub fn walk_history(
+    repo: &Repository,
+    rev: &str,
+    mut on_commit: impl FnMut(&Commit, Vec<FileAnalysis>) -> Result<()>,
+) -> Result<()> {
+    let tip = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("Failed to resolve revision '{}'", rev))?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(tip.id())?;
+    revwalk.simplify_first_parent()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    let mut parent_tree: Option<Tree> = None;
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree().context("Failed to read commit tree")?;
+        let last_modified = commit_time(&commit)?;
+
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let mut file_analyses = Vec::new();
+        for delta in diff.deltas() {
+            let filepath = match delta.new_file().path() {
+                Some(filepath) => filepath.to_path_buf(),
+                None => continue,
+            };
+            let metadata = FileMetadata {
+                filepath: filepath.clone(),
+                repo_path: Some(filepath.clone()),
+                project_path: None,
+                repository: None,
+                last_modified,
+            };
+
+            let file_analysis = repo
+                .find_blob(delta.new_file().id())
+                .ok()
+                .filter(|_| delta.status() != Delta::Deleted)
+                .and_then(|blob| analyze_lines(metadata, blob.content(), None).ok());
+            file_analyses
+                .push(file_analysis.unwrap_or_else(|| empty_file(filepath, last_modified)));
+        }
+
+        on_commit(&commit, file_analyses)?;
+        parent_tree = Some(tree);
+    }
+
+    Ok(())
+}
+
+/// Tracks every todo that ever existed in the first-parent history up to `rev`. Each commit only
+/// re-analyzes the files it changed, and todos are matched between consecutive versions of a
+/// file the same way `diff` matches two scans.
+pub fn mine_history(repo: &Repository, rev: &str) -> Result<Vec<TodoHistory>> {
+    let mut history = History::default();
+    let mut files: HashMap<PathBuf, AnalysisResult> = HashMap::new();
+
+    walk_history(repo, rev, |commit, file_analyses| {
+        let commit_ref = CommitRef::new(commit)?;
+        for file_analysis in file_analyses {
+            let filepath = file_analysis.metadata.filepath.clone();
+            let old = files.remove(&filepath).unwrap_or_else(|| {
+                AnalysisResult::File(empty_file(
+                    filepath.clone(),
+                    file_analysis.metadata.last_modified,
+                ))
+            });
+            let new = AnalysisResult::File(file_analysis);
+
+            history.apply(&old, &new, &commit_ref);
+            files.insert(filepath, new);
+        }
+        Ok(())
+    })?;
+
+    let mut todos = history.todos;
+    todos.sort_by(|a, b| (&a.todo.path, a.todo.line).cmp(&(&b.todo.path, b.todo.line)));
+    Ok(todos)
+}
+
+fn empty_file(filepath: PathBuf, last_modified: DateTime<Utc>) -> FileAnalysis {
+    FileAnalysis {
+        metadata: FileMetadata {
+            repo_path: Some(filepath.clone()),
+            project_path: None,
+            repository: None,
+            filepath,
+            last_modified,
+        },
+        valids: None,
+        invalids: None,
+    }
+}
+
+#[derive(Default)]
+struct History {
+    todos: Vec<TodoHistory>,
+    /// Indices into `todos` of the todos that currently exist, per fingerprint.
+    live: HashMap<String, Vec<usize>>,
+}
+
+impl History {
+    /// Records the changes between two versions of a file.
+    fn apply(&mut self, old: &AnalysisResult, new: &AnalysisResult, commit_ref: &CommitRef) {
+        let file_diff = diff_scans(old, new);
+
+        // Take every changed todo out of `live` before putting any back, since a moved todo can
+        // end up on the line another todo with the same fingerprint was on
+        for todo in file_diff.removed {
+            if let Some(index) = self.take_live(&todo.fingerprint, todo.line) {
+                self.todos[index].resolved = Some(commit_ref.clone());
+            }
+        }
+        let mut updated = Vec::new();
+        for moved in file_diff.moved.into_iter().chain(file_diff.shifted) {
+            if let Some(index) = self.take_live(&moved.todo.fingerprint, moved.from_line) {
+                updated.push((index, moved.todo, false));
+            }
+        }
+        for modified in file_diff.modified {
+            if let Some(index) = self.take_live(&modified.old.fingerprint, modified.old.line) {
+                updated.push((index, modified.new, true));
+            }
+        }
+
+        for (index, todo, is_modified) in updated {
+            self.live
+                .entry(todo.fingerprint.clone())
+                .or_default()
+                .push(index);
+            let history = &mut self.todos[index];
+            history.todo = todo;
+            if is_modified {
+                history.last_modified = commit_ref.clone();
+            }
+        }
+        for todo in file_diff.added {
+            self.live
+                .entry(todo.fingerprint.clone())
+                .or_default()
+                .push(self.todos.len());
+            self.todos.push(TodoHistory {
+                todo,
+                created: commit_ref.clone(),
+                last_modified: commit_ref.clone(),
+                resolved: None,
+            });
+        }
+    }
+
+    /// Removes the live todo with `fingerprint` that was on `line`, or any with that fingerprint.
+    fn take_live(&mut self, fingerprint: &str, line: usize) -> Option<usize> {
+        let indices = self.live.get_mut(fingerprint)?;
+        let position = indices
+            .iter()
+            .position(|&index| self.todos[index].todo.line == line)
+            .or_else(|| indices.len().checked_sub(1))?;
+        Some(indices.remove(position))
+    }
+}
+
+/// One todo per line with its location and text, followed by its history.
+pub fn write_history(todos: &[TodoHistory], writer: &mut dyn Write) -> Result<()> {
+    let describe = |commit_ref: &CommitRef| {
+        format!(
+            "{} by {} ({:.7})",
+            commit_ref.timestamp.format("%Y-%m-%d"),
+            commit_ref.author,
+            commit_ref.commit
+        )
+    };
+
+    for history in todos {
+        let todo = &history.todo;
+        writeln!(
+            writer,
+            "{}:{}  {}",
+            todo.path.display(),
+            todo.line,
+            todo.text
+        )?;
+        writeln!(writer, "    created   {}", describe(&history.created))?;
+        if history.last_modified.commit != history.created.commit {
+            writeln!(writer, "    modified  {}", describe(&history.last_modified))?;
+        }
+        if let Some(resolved) = &history.resolved {
+            writeln!(writer, "    resolved  {}", describe(resolved))?;
+        }
+    }
+
+    let resolved = todos
+        .iter()
+        .filter(|history| history.resolved.is_some())
+        .count();
+    writeln!(
+        writer,
+        "\n{} todos, {} open, {} resolved",
+        todos.len(),
+        todos.len() - resolved,
+        resolved
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+    use std::path::Path;
+
+    #[test]
+    fn test_mine_history() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        let first = test_repo::commit(&repo, &[("a.rs", "// todo: a\n// todo: b\n")], "first");
+        let second = test_repo::commit(&repo, &[("a.rs", "// todo: a2\n// todo: b\n")], "second");
+        let third = test_repo::commit(
+            &repo,
+            &[("a.rs", "\n// todo: a2\n"), ("b.rs", "// todo(): c\n")],
+            "third",
+        );
+
+        let todos = mine_history(&repo, "HEAD")?;
+        let summary: Vec<_> = todos
+            .iter()
+            .map(|history| {
+                (
+                    history.todo.path.as_path(),
+                    history.todo.line,
+                    history.todo.text.as_str(),
+                    history.created.commit.clone(),
+                    history.last_modified.commit.clone(),
+                    history
+                        .resolved
+                        .as_ref()
+                        .map(|resolved| resolved.commit.clone()),
+                )
+            })
+            .collect();
+
+        let (first, second, third) = (first.to_string(), second.to_string(), third.to_string());
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Path::new("a.rs"),
+                    2,
+                    "todo: a2",
+                    first.clone(),
+                    second,
+                    None
+                ),
+                (
+                    Path::new("a.rs"),
+                    2,
+                    "todo: b",
+                    first.clone(),
+                    first,
+                    Some(third.clone())
+                ),
+                (
+                    Path::new("b.rs"),
+                    1,
+                    "// todo(): c",
+                    third.clone(),
+                    third,
+                    None
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
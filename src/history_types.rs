@@ -0,0 +1,24 @@
+use crate::diff_types::DiffTodo;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Lifetime of a todo through the commit history. A todo keeps its identity when it moves or
+/// its text is edited in place; only the last known location and text are kept.
+#[derive(Serialize, Debug)]
+pub struct TodoHistory {
+    #[serde(flatten)]
+    pub todo: DiffTodo,
+    pub created: CommitRef,
+    /// Last commit that changed the todo's text. Same as `created` if it never changed.
+    pub last_modified: CommitRef,
+    /// Commit that removed the todo, if it's gone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<CommitRef>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CommitRef {
+    pub commit: String,
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+}
@@ -0,0 +1,85 @@
+use crate::utils::common_dir;
+use anyhow::{Context, Result};
+use git2::Repository;
+use std::{fs, path::PathBuf};
+
+/// Marks hooks written by `install_hook`, so they can be replaced without `--force`.
+const HOOK_MARKER: &str = "# Installed by todolint";
+
+const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Installed by todolint
+exec todolint check --staged
+";
+
+/// Writes a pre-commit hook that checks the staged content, honoring `core.hooksPath`. An
+/// existing hook that wasn't installed by todolint is only replaced with `force`.
+pub fn install_hook(repo: &Repository, force: bool) -> Result<PathBuf> {
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(hooks_path) if hooks_path.is_relative() => repo
+            .workdir()
+            .unwrap_or_else(|| repo.path())
+            .join(hooks_path),
+        Ok(hooks_path) => hooks_path,
+        // Linked worktrees share the hooks of the main one
+        Err(_) => common_dir(repo).join("hooks"),
+    };
+    let hook_path = hooks_dir.join("pre-commit");
+
+    if let Ok(existing) = fs::read_to_string(&hook_path) {
+        if !force && !existing.contains(HOOK_MARKER) {
+            anyhow::bail!(
+                "{} already exists; pass --force to replace it",
+                hook_path.display()
+            );
+        }
+    }
+
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Failed to create {}", hooks_dir.display()))?;
+    fs::write(&hook_path, PRE_COMMIT_HOOK)
+        .with_context(|| format!("Failed to write {}", hook_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(hook_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+
+    #[test]
+    fn test_install_hook_keeps_foreign_hooks() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        let hook_path = install_hook(&repo, false)?;
+        assert_eq!(hook_path, repo.path().join("hooks/pre-commit"));
+        assert_eq!(fs::read_to_string(&hook_path)?, PRE_COMMIT_HOOK);
+
+        // Reinstalling over our own hook is fine, replacing someone else's isn't
+        install_hook(&repo, false)?;
+        fs::write(&hook_path, "#!/bin/sh\nmake lint\n")?;
+        assert!(install_hook(&repo, false).is_err());
+        install_hook(&repo, true)?;
+        assert_eq!(fs::read_to_string(&hook_path)?, PRE_COMMIT_HOOK);
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_hook_from_linked_worktree() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "")], "first");
+        let worktree = repo.worktree("linked", &dir.path().join("linked"), None)?;
+        let linked = Repository::open_from_worktree(&worktree)?;
+
+        let hook_path = install_hook(&linked, false)?;
+        assert_eq!(
+            hook_path.canonicalize()?,
+            repo.path().join("hooks/pre-commit").canonicalize()?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    analyze_lines,
+    blame_cache::BlameCache,
+    blame_types::BlameConfig,
+    path_analyzer_types::{DirectoryAnalysis, FileAnalysis, FileBlameContext, FileMetadata},
+    utils::{repo_relative_path, repository_root},
+};
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use git2::{Delta, Repository};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Analyzes the content of every file in the index under `path`, reading blobs from the index
+/// rather than the working tree, so partially staged files are scanned as they will be committed.
+/// Unstaged files are included so that baselines and ratchets see the whole commit; see
+/// `staged_paths` for what is actually being committed. With `blame`, staged content is blamed on
+/// top of HEAD, the same way as the working tree is. Paths are relative to the repository root.
+pub fn analyze_index(
+    repo: &Repository,
+    path: &Path,
+    blame: Option<&BlameConfig>,
+    blame_cache: Option<&RefCell<BlameCache>>,
+) -> Result<DirectoryAnalysis> {
+    let prefix = repo_relative_path(repo, path)?;
+    let index = repo.index().context("Failed to read the index")?;
+
+    let mut file_analyses: Vec<FileAnalysis> = Vec::new();
+    for entry in index.iter() {
+        // Conflicted entries have no single staged content, and submodules aren't files
+        let stage = (entry.flags >> 12) & 0x3;
+        if stage != 0 || entry.mode & 0o170000 == 0o160000 {
+            continue;
+        }
+        let filepath = PathBuf::from(String::from_utf8_lossy(&entry.path).as_ref());
+        if !filepath.starts_with(&prefix) {
+            continue;
+        }
+        let blob = repo
+            .find_blob(entry.id)
+            .with_context(|| format!("Failed to read staged '{}'", filepath.display()))?;
+
+        let last_modified = Utc
+            .timestamp_opt(entry.mtime.seconds().into(), 0)
+            .single()
+            .unwrap_or_default();
+
+        let file_blame_context = blame.and_then(|config| {
+            FileBlameContext::for_working_tree(
+                repo,
+                &filepath,
+                blob.content(),
+                last_modified,
+                config,
+                blame_cache,
+            )
+            .ok()
+        });
+        let metadata = FileMetadata {
+            repo_path: Some(filepath.clone()),
+            project_path: None,
+            repository: Some(repository_root(repo)),
+            filepath,
+            last_modified,
+        };
+        // Binary and non UTF-8 blobs fail to analyze, same as on disk
+        if let Ok(file_analysis) =
+            analyze_lines(metadata, blob.content(), file_blame_context.as_ref())
+        {
+            file_analyses.push(file_analysis);
+        }
+    }
+
+    Ok(DirectoryAnalysis {
+        total_files_scanned: file_analyses.len(),
+        last_scan_on: Utc::now(),
+        file_analyses,
+    })
+}
+
+/// Paths of the files staged for commit, i.e. whose content in the index differs from HEAD,
+/// relative to the repository root.
+pub fn staged_paths(repo: &Repository) -> Result<HashSet<PathBuf>> {
+    let index = repo.index().context("Failed to read the index")?;
+    // HEAD is unborn before the first commit, in which case everything in the index is staged
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+        .context("Failed to diff the index against HEAD")?;
+
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() != Delta::Deleted)
+        .filter_map(|delta| delta.new_file().path().map(Path::to_path_buf))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        path_analyzer_types::AnalysisResult,
+        ratchet::{count_todos, tighten_ratchet},
+        ratchet_types::{Ratchet, RatchetGroup},
+        utils::test_repo,
+    };
+    use std::fs;
+
+    #[test]
+    fn test_analyze_index_reads_staged_content() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("same.rs", "// todo(): committed\n")], "first");
+
+        // Staged with an invalid todo, then fixed in the working tree only
+        fs::write(dir.path().join("staged.rs"), "// todo(): staged\n")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("staged.rs"))?;
+        index.write()?;
+        fs::write(dir.path().join("staged.rs"), "// todo: fixed\n")?;
+
+        let analysis = analyze_index(&repo, dir.path(), None, None)?;
+        assert_eq!(analysis.file_analyses.len(), 2);
+        assert_eq!(
+            staged_paths(&repo)?,
+            HashSet::from([PathBuf::from("staged.rs")])
+        );
+
+        let file_analysis = analysis
+            .file_analyses
+            .iter()
+            .find(|file_analysis| file_analysis.metadata.filepath == Path::new("staged.rs"))
+            .unwrap();
+        assert!(file_analysis.valids.is_none());
+        assert_eq!(file_analysis.invalids.as_ref().map(Vec::len), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_staged_scan_against_owner_ratchet() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: committed\n")], "first");
+        let config = BlameConfig::default();
+        let staged_counts = || -> Result<_> {
+            let analysis = analyze_index(&repo, dir.path(), Some(&config), None)?;
+            Ok(count_todos(
+                &AnalysisResult::Directory(analysis),
+                RatchetGroup::Owner,
+            ))
+        };
+
+        let mut ratchet = Ratchet::new(RatchetGroup::Owner, staged_counts()?);
+        assert_eq!(
+            ratchet.counts.keys().collect::<Vec<_>>(),
+            vec!["test@example.com"]
+        );
+
+        // A staged todo counts for nobody yet, and the committed one keeps its owner
+        fs::write(
+            dir.path().join("a.rs"),
+            "// todo: committed\n// todo: staged\n",
+        )?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("a.rs"))?;
+        index.write()?;
+
+        let outcome = tighten_ratchet(&mut ratchet, &staged_counts()?, Path::new(""));
+        assert_eq!(
+            outcome
+                .increased
+                .iter()
+                .map(|change| &change.group[..])
+                .collect::<Vec<_>>(),
+            vec!["not.committed.yet"]
+        );
+        assert!(outcome.decreased.is_empty());
+        assert_eq!(ratchet.counts["test@example.com"].valid, 1);
+        Ok(())
+    }
+}
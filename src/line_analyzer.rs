@@ -1,7 +1,7 @@
 use crate::{
     line_analyzer_types::{
         BlameInfo, CaptureGroupNames, CommentMarker, Delimiter, DelimiterContent, InvalidContent,
-        InvalidTodoComment, TodoCommentResult, ValidContent, ValidTodoComment,
+        InvalidReason, InvalidTodoComment, Span, TodoCommentResult, ValidContent, ValidTodoComment,
     },
     path_analyzer_types::FileBlameContext,
 };
@@ -9,6 +9,10 @@ use anyhow::{Context, Result};
 use regex::Regex;
 use strum::IntoEnumIterator;
 
+/// Bump whenever a change to the analysis or blame gives different results for the same content,
+/// so analyses cached by earlier scans are thrown away.
+pub const ANALYZER_VERSION: u32 = 1;
+
 pub struct LineAnalyzer<'fileblamecontext> {
     // todo: use these attributes
     git_blame_context: Option<&'fileblamecontext FileBlameContext<'fileblamecontext>>,
@@ -35,15 +39,15 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
             None => return Ok(None),
         };
 
-        let marker_content = general_cap
+        let marker_match = general_cap
             .name(CaptureGroupNames::MarkerContent.as_ref())
-            .map(|m| m.as_str())
             .ok_or_else(|| {
                 anyhow::anyhow!(
                     "Missing '{}' capture group",
                     CaptureGroupNames::MarkerContent
                 )
             })?;
+        let marker_content = marker_match.as_str();
 
         let comment_content = general_cap
             .name(CaptureGroupNames::CommentContent.as_ref())
@@ -56,6 +60,7 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
             })?;
 
         let line_number = line_number + 1; // for human readable purposes
+        let column = Self::char_column(line, general_cap.get(0).map_or(0, |m| m.start()));
 
         let blame_info = self.git_blame_context.and_then(|context| {
             BlameInfo::new(context, line_number)
@@ -65,7 +70,21 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
                 .ok()
         });
 
-        if Self::validate(marker_content)? {
+        if let Some(reason) = Self::validate(marker_content)? {
+            Ok(Some(TodoCommentResult::Invalid(InvalidTodoComment {
+                line: line_number,
+                column,
+                line_info: InvalidContent {
+                    full_text: general_cap[0].to_string(),
+                    reason,
+                    span: Span {
+                        start: Self::char_column(line, marker_match.start()),
+                        end: Self::char_column(line, marker_match.end()),
+                    },
+                },
+                blame_info,
+            })))
+        } else {
             let mut delimiters = Vec::new();
 
             for delimiter in Delimiter::iter() {
@@ -81,26 +100,19 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
 
             Ok(Some(TodoCommentResult::Valid(ValidTodoComment {
                 line: line_number,
+                column,
                 line_info: ValidContent {
                     comment: comment_content.to_string(),
                     delimiters: (!delimiters.is_empty()).then_some(delimiters),
                 },
                 blame_info,
             })))
-        } else {
-            Ok(Some(TodoCommentResult::Invalid(InvalidTodoComment {
-                line: line_number,
-                line_info: InvalidContent {
-                    full_text: general_cap[0].to_string(),
-                },
-                blame_info,
-            })))
         }
     }
 
-    /// Validates the contents of a todo (what's between 'todo' and ':'). Returns true if valid,
-    /// false otherwise.
-    fn validate(marker_content: &str) -> Result<bool> {
+    /// Validates the contents of a todo (what's between 'todo' and ':'). Returns the reason if
+    /// invalid, None otherwise.
+    fn validate(marker_content: &str) -> Result<Option<InvalidReason>> {
         let keyword_pattern = r".*?";
         let delimiters = [
             (r"\((?<parens>{})\)", "parens"),
@@ -122,18 +134,21 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
                     .as_str();
 
                 // todo: simplify or use a variable/helper-function?
-                if value.is_empty() || Regex::new(r"[^\w]").unwrap().is_match(value) {
-                    return Ok(false);
+                if value.is_empty() {
+                    return Ok(Some(InvalidReason::EmptyDelimiter));
+                }
+                if Regex::new(r"[^\w]").unwrap().is_match(value) {
+                    return Ok(Some(InvalidReason::InvalidDelimiterContent));
                 }
 
                 if found_delimiters.contains(name) {
-                    return Ok(false); // Duplicate delimiter found
+                    return Ok(Some(InvalidReason::DuplicateDelimiter));
                 }
                 found_delimiters.push(name);
             }
         }
 
-        Ok(found_delimiters.len() <= 4)
+        Ok((found_delimiters.len() > 4).then_some(InvalidReason::DuplicateDelimiter))
     }
 
     /// Extracts content between specified delimiter characters in a given line of text.
@@ -159,6 +174,11 @@ impl<'fileblamecontext> LineAnalyzer<'fileblamecontext> {
             .map(|m| m.as_str()))
     }
 
+    /// 1-based character column of a byte offset within a line.
+    fn char_column(line: &str, byte_offset: usize) -> usize {
+        line[..byte_offset].chars().count() + 1
+    }
+
     fn create_validation_regex(marker: CommentMarker) -> Result<Regex> {
         let prefix = format!(r"//\s*{}\s*", marker);
         let marker_content = format!(r"(?<{}>.*?)", CaptureGroupNames::MarkerContent);
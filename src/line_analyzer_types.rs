@@ -2,13 +2,19 @@ use crate::path_analyzer_types::FileBlameContext;
 use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use email_address::EmailAddress;
-use serde::Serialize;
-use std::str::FromStr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use strum_macros::{AsRefStr, Display, EnumIter};
 
 // == Types ==
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub enum TodoCommentResult {
     Valid(ValidTodoComment),
     Invalid(InvalidTodoComment),
@@ -17,37 +23,91 @@ pub enum TodoCommentResult {
 pub type ValidTodoComment = TodoCommentBase<ValidContent>;
 pub type InvalidTodoComment = TodoCommentBase<InvalidContent>;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct TodoCommentBase<T> {
     pub line: usize,
+    pub column: usize,
     #[serde(flatten)]
     pub line_info: T,
     #[serde(flatten)]
     pub blame_info: Option<BlameInfo>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct ValidContent {
     pub comment: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delimiters: Option<Vec<DelimiterContent>>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct InvalidContent {
     pub full_text: String,
+    pub reason: InvalidReason,
+    /// Columns of the content between the marker and the colon, which is what failed validation.
+    pub span: Span,
 }
 
-#[derive(Serialize, Debug)]
+/// Range of 1-based character columns within a line, end exclusive.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Why a marked comment failed validation. Each reason doubles as a diagnostic code for
+/// reporters.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, EnumIter)]
+#[serde(rename_all = "kebab-case")]
+pub enum InvalidReason {
+    EmptyDelimiter,
+    InvalidDelimiterContent,
+    DuplicateDelimiter,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct DelimiterContent {
     pub delimiter_type: Delimiter,
     pub content: String,
 }
 
-#[derive(Serialize, Debug)]
+/// The commit that last touched a todo's line, per blame. Lines changed in the working tree but
+/// not committed are blamed on an all-zero `commit_id` by "Not Committed Yet", like `git blame`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct BlameInfo {
-    pub email: EmailAddress,
+    pub commit_id: String,
+    /// First line of the commit message.
+    pub summary: Option<String>,
+    pub author: Identity,
+    pub committer: Identity,
+    /// Path of the file in the commit, which differs from the scanned path if it was renamed
+    /// since.
+    pub original_path: Option<PathBuf>,
+    /// Line number in the commit.
+    pub original_line: usize,
+}
+
+/// Author or committer of a commit.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub email: Email,
     pub timestamp: DateTime<Utc>,
+    /// Offset of the identity's timezone from UTC in minutes, as recorded in the commit.
+    pub utc_offset_minutes: i32,
+}
+
+/// Email as recorded in a commit. Git doesn't validate emails, so anything that isn't a valid
+/// address is kept as is rather than replaced.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Email {
+    Valid(#[schemars(with = "String")] EmailAddress),
+    Unparseable(String),
+    /// Not valid UTF-8.
+    Missing,
+    /// Uncommitted changes have no author.
+    NotCommittedYet,
 }
 
 #[derive(Display, AsRefStr)]
@@ -67,7 +127,7 @@ pub struct DelimiterChars {
     close: char,
 }
 
-#[derive(Serialize, Debug, PartialEq, EnumIter)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, EnumIter)]
 pub enum Delimiter {
     Parentheses,
     Braces,
@@ -75,8 +135,64 @@ pub enum Delimiter {
     Angles,
 }
 
+/// Content that identifies a todo independently of the line it sits on.
+pub trait FingerprintSource {
+    fn fingerprint_source(&self) -> String;
+}
+
 // == Impl's ==
 
+impl<T: FingerprintSource> TodoCommentBase<T> {
+    /// Hex encoded hash of the file path and the todo's content. Stays the same when the todo
+    /// moves to another line of the same file.
+    pub fn fingerprint(&self, filepath: &Path) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(filepath.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(self.line_info.fingerprint_source().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl ValidContent {
+    /// Delimiters with their enclosing characters, e.g. `(1)[2]`.
+    pub fn delimiters_text(&self) -> String {
+        self.delimiters
+            .iter()
+            .flatten()
+            .map(|delimiter| {
+                let (open, close) = delimiter.delimiter_type.get_chars().to_tuple();
+                format!("{}{}{}", open, delimiter.content, close)
+            })
+            .collect()
+    }
+}
+
+impl FingerprintSource for ValidContent {
+    fn fingerprint_source(&self) -> String {
+        let delimiters = self
+            .delimiters
+            .iter()
+            .flatten()
+            .map(|delimiter| {
+                format!(
+                    "{}={}",
+                    delimiter.delimiter_type.get_name(),
+                    delimiter.content
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\0");
+        format!("{}\0{}", delimiters, self.comment)
+    }
+}
+
+impl FingerprintSource for InvalidContent {
+    fn fingerprint_source(&self) -> String {
+        self.full_text.clone()
+    }
+}
+
 impl DelimiterChars {
     /// For quick destructuring.
     pub fn to_tuple(&self) -> (char, char) {
@@ -105,36 +221,123 @@ impl Delimiter {
     }
 }
 
+impl InvalidReason {
+    /// Stable diagnostic code, e.g. for rule ids in reports.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvalidReason::EmptyDelimiter => "TL001",
+            InvalidReason::InvalidDelimiterContent => "TL002",
+            InvalidReason::DuplicateDelimiter => "TL003",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvalidReason::EmptyDelimiter => "empty-delimiter",
+            InvalidReason::InvalidDelimiterContent => "invalid-delimiter-content",
+            InvalidReason::DuplicateDelimiter => "duplicate-delimiter",
+        }
+    }
+
+    /// One sentence explaining the violation.
+    pub fn description(&self) -> &'static str {
+        match self {
+            InvalidReason::EmptyDelimiter => "Delimiters after the marker must not be empty.",
+            InvalidReason::InvalidDelimiterContent => {
+                "Delimited content may only contain word characters."
+            }
+            InvalidReason::DuplicateDelimiter => "Each delimiter type may only appear once.",
+        }
+    }
+}
+
 impl BlameInfo {
     pub fn new(file_blame_context: &FileBlameContext, line_number: usize) -> Result<BlameInfo> {
-        // Get hunk for the specified line, then retrieve the corresponding commit
-        let hunk = file_blame_context
-            .blame
-            .get_line(line_number)
-            .context(format!(
-                "No blame information found for line {}",
-                line_number
-            ))?;
-        let commit_id = hunk.final_commit_id();
+        let blamed = file_blame_context.blame_line(line_number)?;
+        if blamed.commit_id.is_zero() {
+            let identity =
+                Identity::not_committed_yet(file_blame_context.modified.unwrap_or_default());
+            return Ok(BlameInfo {
+                commit_id: blamed.commit_id.to_string(),
+                summary: None,
+                author: identity.clone(),
+                committer: identity,
+                original_path: blamed.path,
+                original_line: line_number,
+            });
+        }
+
         let commit = file_blame_context
             .repo
-            .find_commit(commit_id)
-            .with_context(|| format!("Failed to find commit {}", commit_id))?;
-
-        // From commit, extract author email and timestamp
-        let author_email = commit
-            .author()
-            .email()
-            .and_then(|email| EmailAddress::from_str(email).ok())
-            .unwrap_or_else(|| EmailAddress::new_unchecked("unknown@example.com"));
+            .find_commit(blamed.commit_id)
+            .with_context(|| format!("Failed to find commit {}", blamed.commit_id))?;
+
+        let author = Identity::new(&commit.author())?;
+        let committer = Identity::new(&commit.committer())?;
+
+        Ok(BlameInfo {
+            commit_id: blamed.commit_id.to_string(),
+            summary: commit.summary().map(str::to_string),
+            author,
+            committer,
+            original_path: blamed.path,
+            original_line: blamed.line,
+        })
+    }
+
+    pub fn is_committed(&self) -> bool {
+        self.commit_id.bytes().any(|byte| byte != b'0')
+    }
+}
+
+impl Identity {
+    fn new(signature: &git2::Signature) -> Result<Identity> {
+        let when = signature.when();
         let timestamp = Utc
-            .timestamp_opt(commit.time().seconds(), 0)
+            .timestamp_opt(when.seconds(), 0)
             .single()
-            .with_context(|| format!("Invalid timestamp: {}", commit.time().seconds()))?;
+            .with_context(|| format!("Invalid timestamp: {}", when.seconds()))?;
 
-        Ok(BlameInfo {
-            email: author_email,
+        Ok(Identity {
+            name: signature.name().map(str::to_string),
+            email: Email::new(signature.email()),
             timestamp,
+            utc_offset_minutes: when.offset_minutes(),
         })
     }
+
+    /// Stand-in for the author of uncommitted changes, dated when the file was last modified,
+    /// like `git blame` does.
+    fn not_committed_yet(timestamp: DateTime<Utc>) -> Identity {
+        Identity {
+            name: Some("Not Committed Yet".to_string()),
+            email: Email::NotCommittedYet,
+            timestamp,
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+impl Email {
+    pub fn new(email: Option<&str>) -> Email {
+        match email {
+            Some(email) => match EmailAddress::from_str(email) {
+                Ok(address) => Email::Valid(address),
+                Err(_) => Email::Unparseable(email.to_string()),
+            },
+            None => Email::Missing,
+        }
+    }
+}
+
+/// The address, or the recorded text if it isn't valid.
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Email::Valid(address) => write!(f, "{}", address),
+            Email::Unparseable(email) => write!(f, "{}", email),
+            Email::Missing => write!(f, "unknown"),
+            Email::NotCommittedYet => write!(f, "not.committed.yet"),
+        }
+    }
 }
@@ -1,44 +1,590 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
+use clap::Parser;
+use cli::{
+    BaselineArgs, BaselineCommand, BlameArgs, CheckArgs, Cli, Command, ConvertArgs, DiffArgs,
+    DiffFormat, HistoryArgs, HistoryFormat, HookArgs, HookCommand, ReportArgs, ScanArgs,
+    SourceArgs, TrendArgs,
+};
 use git2::Repository;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    path::PathBuf,
+};
+mod baseline;
+use baseline::{
+    apply_baseline, create_baseline, load_baseline, prune_baseline, save_baseline,
+    DEFAULT_BASELINE_PATH,
 };
-use utils::print_json;
+mod baseline_types;
+mod blame;
+use blame::blame_config;
+mod blame_cache;
+use blame_cache::BlameCache;
+mod blame_types;
+use blame_types::BlameConfig;
+mod changed_lines;
+use changed_lines::ChangedLines;
+mod cli;
+mod diff;
+use diff::{diff_scans, write_diff};
+mod diff_types;
+mod history;
+use history::{mine_history, write_history};
+mod history_types;
+mod hook;
+use hook::install_hook;
+mod index_analyzer;
+use index_analyzer::{analyze_index, staged_paths};
 mod line_analyzer;
 mod path_analyzer;
 mod path_analyzer_types;
 use path_analyzer_types::{
     AnalysisConfig, AnalysisResult, DirAnalysisConfig, DirectoryAnalysis, FileAnalysis,
-    FileAnalysisConfig, FileBlameContext, FileMetadata,
+    FileAnalysisConfig, FileBlameContext, FileMetadata, FromRef,
 };
 mod line_analyzer_types;
 use line_analyzer_types::TodoCommentResult;
+mod ratchet;
+use ratchet::{count_todos, load_ratchet, save_ratchet, tighten_ratchet};
+mod ratchet_types;
+use ratchet_types::{Ratchet, RatchetGroup, RatchetOutcome};
+mod reporters;
+mod repositories;
+use repositories::Repositories;
+mod rev_analyzer;
+use reporters::{load_report, Column, ReportOptions, StreamingReporter};
+use rev_analyzer::analyze_rev;
+mod scan_cache;
+use scan_cache::ScanCache;
+mod scan_cache_types;
+use scan_cache_types::ScannedFile;
+mod schema;
+use schema::report_schema;
+mod trend;
+use trend::{todo_trend, write_trend_csv, write_trend_svg};
+mod trend_types;
+use trend_types::TrendFormat;
 mod utils;
 use line_analyzer::LineAnalyzer;
 use std::path::Path;
+use utils::{project_root, repo_relative_path, repository_root, write_json};
 use walkdir::WalkDir;
 
 fn main() -> Result<()> {
-    let path = Path::new("test/one_valid.txt");
-    let repo = Repository::discover(path).unwrap();
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Scan(args) => scan(&args),
+        Command::Convert(args) => convert(&args),
+        Command::Schema => write_json(&mut io::stdout(), &report_schema()),
+        Command::Diff(args) => diff(&args),
+        Command::Baseline(args) => baseline(&args),
+        Command::Check(args) => check(&args),
+        Command::Hook(args) => hook(&args),
+        Command::History(args) => history(&args),
+        Command::Trend(args) => trend(&args),
+    }
+}
+
+fn scan(args: &ScanArgs) -> Result<()> {
+    // Content from git is all read up front, so there's nothing to stream
+    let streaming_reporter = args.report.format.streaming_reporter();
+    if let Some(reporter) = streaming_reporter.filter(|_| !args.source.reads_from_git()) {
+        return stream_scan(&args.source, reporter, &args.report);
+    }
+
+    let mut analysis = scan_path(&args.source)?;
+    restrict_report(&args.source, &mut analysis)?;
+
+    write_report(&analysis, &args.report)
+}
+
+/// Reports each file as soon as it has been analyzed.
+fn stream_scan(
+    source: &SourceArgs,
+    mut reporter: Box<dyn StreamingReporter>,
+    report_args: &ReportArgs,
+) -> Result<()> {
+    let path = source.path.as_path();
+    let repo = Repository::discover(path).ok();
+    let blame_config = source_blame_config(repo.as_ref(), source)?;
+    let blame_cache = source_blame_cache(blame_config.as_ref(), source);
+    let scan_cache = source_scan_cache(repo.as_ref(), blame_config.as_ref(), source);
+    let dir_analysis_config = dir_analysis_config(
+        repo.as_ref(),
+        blame_config.as_ref(),
+        blame_cache.as_ref(),
+        scan_cache.as_ref(),
+        source.skip_submodules,
+    );
+    let changed_lines = match &source.base {
+        Some(base) => Some(changed_lines(repo.as_ref(), base)?),
+        None => None,
+    };
+
+    let project_root = repo.as_ref().and_then(|repo| project_root(repo).ok());
+
+    let mut writer = open_writer(report_args.output.as_deref())?;
+    reporter.start(Some(path), &mut writer)?;
+    let mut report_file = |mut file_analysis: FileAnalysis| {
+        if let Some(project_root) = &project_root {
+            set_project_path(&mut file_analysis.metadata, project_root);
+        }
+        if let Some(changed_lines) = &changed_lines {
+            changed_lines.restrict_file(&mut file_analysis);
+        }
+        reporter.file(&file_analysis, &mut writer)
+    };
+    if path.is_dir() {
+        for file_analysis in walk_dir(path, &dir_analysis_config) {
+            report_file(file_analysis)?;
+        }
+    } else {
+        report_file(analyze_file(
+            path,
+            &dir_analysis_config.file_analysis_config,
+        )?)?;
+    }
+    save_blame_cache(blame_cache.as_ref());
+    save_scan_cache(scan_cache.as_ref());
+    reporter.finish(&mut writer)
+}
+
+/// Analyzes a file or directory, with blame from the repository it's in if any. The whole source
+/// is analyzed, see `restrict_report`.
+fn scan_path(source: &SourceArgs) -> Result<AnalysisResult> {
+    let repo = Repository::discover(&source.path).ok();
+    let mut analysis = analyze_source(source, repo.as_ref())?;
+
+    if let Some(project_root) = repo.as_ref().and_then(|repo| project_root(repo).ok()) {
+        for file_analysis in analysis.file_analyses_mut() {
+            set_project_path(&mut file_analysis.metadata, &project_root);
+        }
+    }
+    Ok(analysis)
+}
+
+fn analyze_source(source: &SourceArgs, repo: Option<&Repository>) -> Result<AnalysisResult> {
+    let path = source.path.as_path();
+    let blame_config = source_blame_config(repo, source)?;
+
+    if source.staged {
+        let repo = repo.context("--staged needs the scanned path to be in a git repository")?;
+        let blame_cache = source_blame_cache(blame_config.as_ref(), source);
+        let analysis = analyze_index(repo, path, blame_config.as_ref(), blame_cache.as_ref())?;
+        save_blame_cache(blame_cache.as_ref());
+        return Ok(AnalysisResult::Directory(analysis));
+    }
+    if let Some(rev) = &source.rev {
+        let repo = repo.context("--rev needs the scanned path to be in a git repository")?;
+        let prefix = repo_relative_path(repo, path)?;
+        let analysis = analyze_rev(repo, rev, &prefix, blame_config.as_ref())?;
+        return Ok(AnalysisResult::Directory(analysis));
+    }
+
+    let blame_cache = source_blame_cache(blame_config.as_ref(), source);
+    let scan_cache = source_scan_cache(repo, blame_config.as_ref(), source);
+    let dir_analysis_config = dir_analysis_config(
+        repo,
+        blame_config.as_ref(),
+        blame_cache.as_ref(),
+        scan_cache.as_ref(),
+        source.skip_submodules,
+    );
+    let analysis_config = AnalysisConfig::new_from_dir_config(&dir_analysis_config);
+
+    let analysis = analyze_path(path, &analysis_config)?;
+    save_blame_cache(blame_cache.as_ref());
+    save_scan_cache(scan_cache.as_ref());
+    Ok(analysis)
+}
+
+/// Identifies a file in a repository nested in the project, such as a submodule, by its path
+/// relative to `project_root` too, see `utils::project_root`.
+fn set_project_path(metadata: &mut FileMetadata, project_root: &Path) {
+    let prefix = metadata
+        .repository
+        .as_deref()
+        .and_then(|repository| fs::canonicalize(repository).ok())
+        .and_then(|repository| {
+            repository
+                .strip_prefix(project_root)
+                .ok()
+                .map(Path::to_path_buf)
+        });
+    metadata.project_path = match (prefix, &metadata.repo_path) {
+        (Some(prefix), Some(repo_path)) if !prefix.as_os_str().is_empty() => {
+            Some(prefix.join(repo_path))
+        }
+        _ => None,
+    };
+}
+
+/// With a base ref, only keeps todos on lines changed since then, and with `--staged`, only files
+/// staged for commit. Applied after baselines and ratchets have seen the whole scan, since a
+/// todo that isn't reported still exists.
+fn restrict_report(source: &SourceArgs, analysis: &mut AnalysisResult) -> Result<()> {
+    let repo = Repository::discover(&source.path).ok();
+    if let Some(base) = &source.base {
+        changed_lines(repo.as_ref(), base)?.restrict(analysis);
+    }
+    if let (true, Some(repo), AnalysisResult::Directory(dir_analysis)) =
+        (source.staged, &repo, analysis)
+    {
+        let staged = staged_paths(repo)?;
+        dir_analysis
+            .file_analyses
+            .retain(|file_analysis| staged.contains(&file_analysis.metadata.filepath));
+    }
+    Ok(())
+}
+
+fn changed_lines(repo: Option<&Repository>, base: &str) -> Result<ChangedLines> {
+    let repo = repo.context("--base needs the scanned path to be in a git repository")?;
+    ChangedLines::new(repo, base)
+}
+
+fn source_blame_config(
+    repo: Option<&Repository>,
+    source: &SourceArgs,
+) -> Result<Option<BlameConfig>> {
+    repo.map(|repo| blame_config(repo, &source.blame))
+        .transpose()
+}
+
+fn source_blame_cache(
+    blame_config: Option<&BlameConfig>,
+    source: &SourceArgs,
+) -> Option<RefCell<BlameCache>> {
+    if source.blame.no_blame_cache {
+        return None;
+    }
+    blame_config.map(|blame_config| RefCell::new(BlameCache::new(blame_config)))
+}
+
+/// The cache only speeds up later scans, so failing to write it doesn't fail this one.
+fn save_blame_cache(blame_cache: Option<&RefCell<BlameCache>>) {
+    if let Some(blame_cache) = blame_cache {
+        if let Err(err) = blame_cache.borrow_mut().save() {
+            eprintln!("Warning: failed to save the blame cache: {:#}", err);
+        }
+    }
+}
+
+/// Kept in the git directory of the scanned repository unless given on the command line.
+fn source_scan_cache(
+    repo: Option<&Repository>,
+    blame_config: Option<&BlameConfig>,
+    source: &SourceArgs,
+) -> Option<RefCell<ScanCache>> {
+    if source.no_scan_cache {
+        return None;
+    }
+    let path = match &source.scan_cache {
+        Some(path) => path.clone(),
+        None => repo?.path().join("todolint").join("scan-cache.json"),
+    };
+    Some(RefCell::new(ScanCache::load(&path, blame_config)))
+}
 
+fn save_scan_cache(scan_cache: Option<&RefCell<ScanCache>>) {
+    if let Some(scan_cache) = scan_cache {
+        if let Err(err) = scan_cache.borrow_mut().save() {
+            eprintln!("Warning: failed to save the scan cache: {:#}", err);
+        }
+    }
+}
+
+fn dir_analysis_config<'a>(
+    repo: Option<&'a Repository>,
+    blame_config: Option<&'a BlameConfig>,
+    blame_cache: Option<&'a RefCell<BlameCache>>,
+    scan_cache: Option<&'a RefCell<ScanCache>>,
+    skip_nested_repos: bool,
+) -> DirAnalysisConfig<'a> {
     let file_analysis_config = FileAnalysisConfig {
-        repo: Some(&repo),
+        repo,
+        blame_config,
+        blame_cache,
+        scan_cache,
         include_files: None,
     };
 
-    let dir_analysis_config = DirAnalysisConfig {
+    DirAnalysisConfig {
         file_analysis_config: file_analysis_config,
         exclude_dirs: None,
+        skip_nested_repos,
+    }
+}
+
+fn convert(args: &ConvertArgs) -> Result<()> {
+    let analysis = load_report(&args.input)?;
+
+    write_report(&analysis, &args.report)
+}
+
+fn diff(args: &DiffArgs) -> Result<()> {
+    let repo = Repository::discover(".").ok();
+    let old = load_scan(&args.old, repo.as_ref())?;
+    let new = load_scan(&args.new, repo.as_ref())?;
+
+    let scan_diff = diff_scans(&old, &new);
+    match args.format {
+        DiffFormat::Terminal => write_diff(&scan_diff, &mut io::stdout()),
+        DiffFormat::Json => write_json(&mut io::stdout(), &scan_diff),
+    }
+}
+
+fn baseline(args: &BaselineArgs) -> Result<()> {
+    match &args.command {
+        BaselineCommand::Create(args) => {
+            let baseline = create_baseline(&scan_path(&baseline_source(&args.path))?);
+            save_baseline(&baseline, &args.file)?;
+            println!(
+                "Baselined {} invalid todos in {}",
+                baseline.entries.len(),
+                args.file.display()
+            );
+            Ok(())
+        }
+        BaselineCommand::Prune(args) => {
+            let source = baseline_source(&args.path);
+            let mut baseline = load_baseline(&args.file)?;
+            let outcome =
+                apply_baseline(&mut scan_path(&source)?, &baseline, &scan_scope(&source)?);
+            prune_baseline(&mut baseline, &outcome.stale);
+            save_baseline(&baseline, &args.file)?;
+            println!(
+                "Removed {} stale entries from {}",
+                outcome.stale.len(),
+                args.file.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn baseline_source(path: &Path) -> SourceArgs {
+    SourceArgs {
+        path: path.to_path_buf(),
+        base: None,
+        staged: false,
+        rev: None,
+        skip_submodules: false,
+        scan_cache: None,
+        no_scan_cache: false,
+        blame: BlameArgs::default(),
+    }
+}
+
+fn check(args: &CheckArgs) -> Result<()> {
+    let mut analysis = scan_path(&args.source)?;
+    let scope = scan_scope(&args.source)?;
+
+    // Baselined todos still count towards the ratchet
+    let ratchet_outcome = match &args.ratchet {
+        Some(path) => Some(check_ratchet(&analysis, path, args.ratchet_by, &scope)?),
+        None => None,
     };
 
-    let analysis_config = AnalysisConfig::new_from_dir_config(&dir_analysis_config);
-    // let analysis_config = AnalysisConfig::default();
+    let default_baseline = PathBuf::from(DEFAULT_BASELINE_PATH);
+    let baseline_path = match &args.baseline {
+        Some(path) => Some(path),
+        None => Some(&default_baseline).filter(|path| path.is_file()),
+    };
+    let outcome = match baseline_path {
+        Some(path) => Some(apply_baseline(&mut analysis, &load_baseline(path)?, &scope)),
+        None => None,
+    };
+    restrict_report(&args.source, &mut analysis)?;
+
+    write_report(&analysis, &args.report)?;
+
+    let mut failures = Vec::new();
+    let invalid_count: usize = analysis
+        .file_analyses()
+        .iter()
+        .map(|file_analysis| file_analysis.invalids.as_ref().map_or(0, Vec::len))
+        .sum();
+    if invalid_count > 0 && ratchet_outcome.is_none() {
+        failures.push(format!(
+            "{} invalid todos not in the baseline",
+            invalid_count
+        ));
+    }
+
+    if let (Some(path), Some(outcome)) = (baseline_path, outcome) {
+        for entry in &outcome.stale {
+            eprintln!(
+                "warning: baselined todo no longer exists: {}:{}  {}",
+                entry.path.display(),
+                entry.line,
+                entry.text
+            );
+        }
+        if !outcome.stale.is_empty() {
+            eprintln!(
+                "Run `todolint baseline prune {} --file {}` to remove {} stale entries",
+                args.source.path.display(),
+                path.display(),
+                outcome.stale.len()
+            );
+            if args.fail_on_stale {
+                failures.push(format!("{} stale baseline entries", outcome.stale.len()));
+            }
+        }
+    }
+
+    if let Some(outcome) = ratchet_outcome {
+        for change in &outcome.increased {
+            eprintln!(
+                "error: {} todos in '{}' increased from {} to {}",
+                change.kind, change.group, change.old, change.new
+            );
+        }
+        if !outcome.increased.is_empty() {
+            failures.push(format!(
+                "{} ratcheted todo counts increased",
+                outcome.increased.len()
+            ));
+        }
+    }
+
+    if !failures.is_empty() {
+        anyhow::bail!("Check failed: {}", failures.join(", "));
+    }
+    Ok(())
+}
+
+fn hook(args: &HookArgs) -> Result<()> {
+    match &args.command {
+        HookCommand::Install(args) => {
+            let repo = Repository::discover(".").context("Not in a git repository")?;
+            let hook_path = install_hook(&repo, args.force)?;
+            println!("Installed pre-commit hook at {}", hook_path.display());
+            Ok(())
+        }
+    }
+}
+
+fn history(args: &HistoryArgs) -> Result<()> {
+    let repo = Repository::discover(".").context("Not in a git repository")?;
+    let todos = mine_history(&repo, &args.rev)?;
+
+    match args.format {
+        HistoryFormat::Terminal => write_history(&todos, &mut io::stdout()),
+        HistoryFormat::Json => write_json(&mut io::stdout(), &todos),
+    }
+}
+
+fn trend(args: &TrendArgs) -> Result<()> {
+    let repo = Repository::discover(".").context("Not in a git repository")?;
+    let points = todo_trend(&repo, &args.rev, args.sample, args.every as usize)?;
+
+    let mut writer = open_writer(args.output.as_deref())?;
+    match args.format {
+        TrendFormat::Csv => write_trend_csv(&points, &mut writer),
+        TrendFormat::Json => write_json(&mut writer, &points),
+        TrendFormat::Svg => write_trend_svg(&points, &mut writer),
+    }
+}
+
+/// The part of the project `source` covers, relative to the root of the top-level repository
+/// like `FileMetadata::stable_path`, even when scanning a submodule. Empty for the whole
+/// repository, and outside of one, where there's no other root to go by.
+fn scan_scope(source: &SourceArgs) -> Result<PathBuf> {
+    match Repository::discover(&source.path) {
+        Ok(repo) => Ok(fs::canonicalize(&source.path)?
+            .strip_prefix(project_root(&repo)?)?
+            .to_path_buf()),
+        Err(_) => Ok(PathBuf::new()),
+    }
+}
+
+/// Compares the todo counts of `analysis` against the ratchet at `path`, creating it if it
+/// doesn't exist yet, and saves it if counts dropped. `scope` is what `analysis` covers, see
+/// `scan_scope`.
+fn check_ratchet(
+    analysis: &AnalysisResult,
+    path: &Path,
+    group_by: Option<RatchetGroup>,
+    scope: &Path,
+) -> Result<RatchetOutcome> {
+    if !path.exists() {
+        if !scope.as_os_str().is_empty() {
+            anyhow::bail!(
+                "Ratchet {} doesn't exist yet; create it by checking the whole repository",
+                path.display()
+            );
+        }
+        let group_by = group_by.unwrap_or(RatchetGroup::Directory);
+        let ratchet = Ratchet::new(group_by, count_todos(analysis, group_by));
+        save_ratchet(&ratchet, path)?;
+        eprintln!("Created ratchet {}", path.display());
+        return Ok(RatchetOutcome::default());
+    }
+
+    let mut ratchet = load_ratchet(path)?;
+    if group_by.is_some_and(|group_by| group_by != ratchet.group_by) {
+        anyhow::bail!(
+            "Ratchet {} groups counts by {:?}; delete it to change grouping",
+            path.display(),
+            ratchet.group_by
+        );
+    }
+
+    let counts = count_todos(analysis, ratchet.group_by);
+    let outcome = tighten_ratchet(&mut ratchet, &counts, scope);
+    if !outcome.decreased.is_empty() {
+        save_ratchet(&ratchet, path)?;
+        for change in &outcome.decreased {
+            eprintln!(
+                "Tightened {} todos in '{}' from {} to {}",
+                change.kind, change.group, change.old, change.new
+            );
+        }
+    }
+    Ok(outcome)
+}
+
+/// Loads a saved report, or scans a git revision if `source` isn't a file.
+fn load_scan(source: &str, repo: Option<&Repository>) -> Result<AnalysisResult> {
+    let path = Path::new(source);
+    if path.is_file() {
+        return load_report(path);
+    }
+
+    let repo = repo.with_context(|| {
+        format!(
+            "'{}' is not a report file, and there is no git repository to look up a revision in",
+            source
+        )
+    })?;
+    let analysis = analyze_rev(repo, source, Path::new(""), None)?;
+    Ok(AnalysisResult::Directory(analysis))
+}
+
+fn open_writer(output: Option<&Path>) -> Result<Box<dyn Write>> {
+    Ok(match output {
+        Some(output) => Box::new(File::create(output).context("Failed to create output file")?),
+        None => Box::new(io::stdout().lock()),
+    })
+}
 
-    let analysis = analyze_path(&path, &analysis_config)?;
-    print_json(&analysis);
+fn write_report(analysis: &AnalysisResult, args: &ReportArgs) -> Result<()> {
+    let mut writer = open_writer(args.output.as_deref())?;
+    let report_options = ReportOptions {
+        styled: args
+            .color
+            .enabled(args.output.is_none() && io::stdout().is_terminal()),
+        columns: args.columns.clone().unwrap_or_else(Column::all),
+    };
+    args.format
+        .reporter(report_options)
+        .report(analysis, &mut writer)?;
 
     Ok(())
 }
@@ -65,15 +611,7 @@ fn analyze_path(path: &Path, analysis_config: &AnalysisConfig) -> Result<Analysi
 }
 
 fn analyze_dir(dirpath: &Path, dir_analysis_config: &DirAnalysisConfig) -> DirectoryAnalysis {
-    let file_analyses: Vec<FileAnalysis> = WalkDir::new(dirpath)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .filter_map(|entry| {
-            let path = entry.path();
-            analyze_file(path, &dir_analysis_config.file_analysis_config).ok()
-        })
-        .collect();
+    let file_analyses: Vec<FileAnalysis> = walk_dir(dirpath, dir_analysis_config).collect();
 
     let total_files_scanned = file_analyses.len();
 
@@ -84,26 +622,134 @@ fn analyze_dir(dirpath: &Path, dir_analysis_config: &DirAnalysisConfig) -> Direc
     }
 }
 
+/// Lazily analyzes each file under `dirpath`, so results can be used as soon as a file is done.
+fn walk_dir<'a>(
+    dirpath: &Path,
+    dir_analysis_config: &'a DirAnalysisConfig,
+) -> impl Iterator<Item = FileAnalysis> + 'a {
+    let file_analysis_config = &dir_analysis_config.file_analysis_config;
+    let mut repositories = Repositories::new(dirpath, file_analysis_config.repo);
+
+    let skip_nested_repos = dir_analysis_config.skip_nested_repos;
+
+    WalkDir::new(dirpath)
+        .into_iter()
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            // Git's own files aren't source, and hold the caches written by scans
+            if entry.file_name() == ".git" {
+                return false;
+            }
+            !(skip_nested_repos && entry.file_type().is_dir() && entry.path().join(".git").exists())
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(move |entry| {
+            let path = entry.path();
+            // Files in nested repositories and submodules are blamed in their own repository
+            let config = FileAnalysisConfig {
+                repo: repositories.repo_for(path),
+                ..FileAnalysisConfig::from_ref(file_analysis_config)
+            };
+            analyze_file(path, &config).ok()
+        })
+}
+
 fn analyze_file(filepath: &Path, config: &FileAnalysisConfig) -> Result<FileAnalysis> {
-    let file = File::open(filepath).context("Failed to open file")?;
+    let mut file = File::open(filepath).context("Failed to open file")?;
     let metadata = file.metadata().context("Failed to get file metadata")?;
-    let reader = BufReader::new(file);
 
+    // Blame needs the path relative to the repository root, wherever the scan started from
+    let repo_path = config
+        .repo
+        .and_then(|repo| repo_relative_path(repo, filepath).ok());
+    let file_metadata = FileMetadata {
+        filepath: filepath.to_path_buf(),
+        repo_path: repo_path.clone(),
+        project_path: None,
+        repository: config.repo.map(repository_root),
+        last_modified: metadata.modified()?.into(),
+    };
+
+    // Files unmodified since the last scan are taken from the cache, without reading them if
+    // their mtime and size are unchanged
+    let scanned_file = match config.scan_cache {
+        Some(_) => Some(ScannedFile::new(
+            filepath,
+            &metadata,
+            config.repo.zip(repo_path.as_deref()),
+        )?),
+        None => None,
+    };
+    let cached = |content: Option<&[u8]>| {
+        let (scan_cache, scanned_file) = config.scan_cache.zip(scanned_file.as_ref())?;
+        let file_analysis = scan_cache
+            .borrow_mut()
+            .get(scanned_file, content, config.repo)?;
+        Some(FileAnalysis {
+            metadata: file_metadata.clone(),
+            ..file_analysis
+        })
+    };
+    if let Some(file_analysis) = cached(None) {
+        return Ok(file_analysis);
+    }
+
+    let default_blame_config = BlameConfig::default();
+    let blame_config = config.blame_config.unwrap_or(&default_blame_config);
+    if config.repo.is_none() && scanned_file.is_none() {
+        return analyze_lines(file_metadata, BufReader::new(file), None);
+    }
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)
+        .context("Failed to read file")?;
+    if let Some(file_analysis) = cached(Some(&content)) {
+        return Ok(file_analysis);
+    }
+
+    // The file on disk may differ from the committed one, so blame its actual content
+    let file_blame_context = config.repo.zip(repo_path).and_then(|(repo, repo_path)| {
+        FileBlameContext::for_working_tree(
+            repo,
+            &repo_path,
+            &content,
+            file_metadata.last_modified,
+            blame_config,
+            config.blame_cache,
+        )
+        .ok()
+    });
+
+    let file_analysis = analyze_lines(
+        file_metadata.clone(),
+        content.as_slice(),
+        file_blame_context.as_ref(),
+    )?;
+    if let Some((scan_cache, scanned_file)) = config.scan_cache.zip(scanned_file.as_ref()) {
+        scan_cache
+            .borrow_mut()
+            .insert(scanned_file, &content, &file_analysis);
+    }
+    Ok(file_analysis)
+}
+
+/// Analyzes a file's content line by line, regardless of where the content was read from.
+fn analyze_lines(
+    metadata: FileMetadata,
+    reader: impl BufRead,
+    file_blame_context: Option<&FileBlameContext>,
+) -> Result<FileAnalysis> {
     // todo: create with a FileAnalysis::new?
     let mut file_analysis = FileAnalysis {
-        metadata: FileMetadata {
-            filepath: filepath.to_path_buf(),
-            last_modified: metadata.modified()?.into(),
-        },
+        metadata,
         valids: None,
         invalids: None,
     };
 
-    let file_blame_context = config
-        .repo
-        .and_then(|repo| FileBlameContext::new(repo, filepath).ok());
-
-    let line_analyzer_obj = LineAnalyzer::new(file_blame_context.as_ref())?;
+    let line_analyzer_obj = LineAnalyzer::new(file_blame_context)?;
 
     for (line_number, line) in reader.lines().enumerate() {
         let line = line.context("Failed to read line")?;
@@ -132,6 +778,7 @@ fn analyze_file(filepath: &Path, config: &FileAnalysisConfig) -> Result<FileAnal
 #[cfg(test)]
 mod tests {
     use super::*;
+    use utils::test_repo;
 
     #[test]
     fn test_analyze_dir() -> Result<()> {
@@ -145,6 +792,54 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_walk_dir_tags_nested_repos() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "outer");
+        let inner = Repository::init(dir.path().join("vendor/inner"))?;
+        test_repo::commit(&inner, &[("b.rs", "// todo: b\n")], "inner");
+
+        let mut dir_analysis_config = dir_analysis_config(Some(&repo), None, None, None, false);
+        let mut file_analyses: Vec<FileAnalysis> = walk_dir(dir.path(), &dir_analysis_config)
+            .filter(|file_analysis| file_analysis.valids.is_some())
+            .collect();
+        file_analyses.sort_by(|a, b| a.metadata.filepath.cmp(&b.metadata.filepath));
+
+        let tags: Vec<_> = file_analyses
+            .iter()
+            .map(|file_analysis| {
+                (
+                    file_analysis.metadata.repo_path.clone(),
+                    file_analysis.metadata.repository.clone(),
+                    file_analysis.valids.as_ref().unwrap()[0]
+                        .blame_info
+                        .is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                (
+                    Some(PathBuf::from("a.rs")),
+                    Some(repository_root(&repo)),
+                    true
+                ),
+                (
+                    Some(PathBuf::from("b.rs")),
+                    Some(repository_root(&inner)),
+                    true
+                ),
+            ]
+        );
+
+        dir_analysis_config.skip_nested_repos = true;
+        let skipped = walk_dir(dir.path(), &dir_analysis_config)
+            .any(|file_analysis| file_analysis.metadata.filepath.ends_with("b.rs"));
+        assert!(!skipped);
+        Ok(())
+    }
+
     #[test]
     fn test_analyze_file_valid() -> Result<()> {
         let filename = Path::new("test/valid.txt");
@@ -1,25 +1,36 @@
-use crate::line_analyzer_types::{InvalidTodoComment, ValidTodoComment};
+use crate::{
+    blame::hunks_of,
+    blame_cache::BlameCache,
+    blame_types::{BlameConfig, Hunk},
+    line_analyzer_types::{InvalidTodoComment, ValidTodoComment},
+    scan_cache::ScanCache,
+    utils::normalize_path,
+};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use git2::{Blame, BlameOptions, Repository};
-use serde::Serialize;
-use std::path::{Path, PathBuf};
+use git2::{Oid, Repository};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+};
 
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub enum AnalysisResult {
     Directory(DirectoryAnalysis),
     File(FileAnalysis),
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct DirectoryAnalysis {
     pub total_files_scanned: usize,
     pub last_scan_on: DateTime<Utc>,
     pub file_analyses: Vec<FileAnalysis>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct FileAnalysis {
     pub metadata: FileMetadata,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,9 +39,19 @@ pub struct FileAnalysis {
     pub invalids: Option<Vec<InvalidTodoComment>>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct FileMetadata {
     pub filepath: PathBuf,
+    /// Path relative to the root of the repository the file belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_path: Option<PathBuf>,
+    /// For a file in a nested repository or submodule, its path relative to the root of the
+    /// top-level repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_path: Option<PathBuf>,
+    /// Root of the working tree of the repository the file belongs to, e.g. a submodule's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<PathBuf>,
     pub last_modified: DateTime<Utc>,
 }
 
@@ -38,13 +59,22 @@ pub struct FileMetadata {
 // borrowing from (which is an arg in the `new` method).
 pub struct FileBlameContext<'repo> {
     pub repo: &'repo Repository,
-    pub blame: Blame<'repo>,
+    pub hunks: Vec<Hunk>,
+    pub config: &'repo BlameConfig,
+    /// When the working tree file was last modified, which uncommitted lines are dated with.
+    pub modified: Option<DateTime<Utc>>,
 }
 
 /// Options for file analysis, wrapped in Option to indicate availability and user-enabled status
 /// as determined by the parent entity.
 pub struct FileAnalysisConfig<'a> {
     pub repo: Option<&'a Repository>,
+    /// How todos are blamed when `repo` is set. Plain `git blame` if `None`.
+    pub blame_config: Option<&'a BlameConfig>,
+    /// Blame of unmodified files from earlier scans of `repo`.
+    pub blame_cache: Option<&'a RefCell<BlameCache>>,
+    /// Analyses of files unmodified since earlier scans.
+    pub scan_cache: Option<&'a RefCell<ScanCache>>,
     /// Additional file extensions to include. Do not include the leading dot.
     pub include_files: Option<&'static [&'static str]>,
 }
@@ -55,6 +85,8 @@ pub struct DirAnalysisConfig<'a> {
     pub file_analysis_config: FileAnalysisConfig<'a>,
     // Directories to exclude, relative to the root of the project.
     pub exclude_dirs: Option<&'static [&'static str]>,
+    /// Don't descend into submodules and other repositories nested in the analyzed directory.
+    pub skip_nested_repos: bool,
 }
 
 /// Options for project analysis.
@@ -63,6 +95,36 @@ pub struct AnalysisConfig<'a> {
     pub file_analysis_config: FileAnalysisConfig<'a>,
 }
 
+impl AnalysisResult {
+    /// All file analyses in the result, regardless of whether a file or directory was scanned.
+    pub fn file_analyses(&self) -> &[FileAnalysis] {
+        match self {
+            AnalysisResult::Directory(dir_analysis) => &dir_analysis.file_analyses,
+            AnalysisResult::File(file_analysis) => std::slice::from_ref(file_analysis),
+        }
+    }
+
+    pub fn file_analyses_mut(&mut self) -> &mut [FileAnalysis] {
+        match self {
+            AnalysisResult::Directory(dir_analysis) => &mut dir_analysis.file_analyses,
+            AnalysisResult::File(file_analysis) => std::slice::from_mut(file_analysis),
+        }
+    }
+}
+
+impl FileMetadata {
+    /// Path the file's todos are identified by in fingerprints. Relative to the root of the
+    /// top-level repository when the file is in one, so it's the same wherever todolint is run
+    /// from, and for scans of the index or a commit.
+    pub fn stable_path(&self) -> PathBuf {
+        match (&self.project_path, &self.repo_path) {
+            (Some(project_path), _) => project_path.clone(),
+            (None, Some(repo_path)) => repo_path.clone(),
+            (None, None) => normalize_path(&self.filepath),
+        }
+    }
+}
+
 impl<'a> Default for AnalysisConfig<'a> {
     fn default() -> Self {
         Self {
@@ -84,11 +146,40 @@ impl<'a> AnalysisConfig<'a> {
 impl<'repo> FileBlameContext<'repo> {
     // todo: I don't like that Repository is a param, but its that way bc of ownership rules
     // refactor in the future
-    pub fn new(repo: &'repo Repository, file_path: &Path) -> Result<Self> {
-        let mut blame_opts = BlameOptions::new();
+    pub fn new(
+        repo: &'repo Repository,
+        file_path: &Path,
+        config: &'repo BlameConfig,
+    ) -> Result<Self> {
+        let mut blame_opts = config.options();
+        let blame = repo.blame_file(file_path, Some(&mut blame_opts))?;
+
+        Ok(FileBlameContext {
+            repo,
+            hunks: hunks_of(&blame),
+            config,
+            modified: None,
+        })
+    }
+
+    /// Blame as of `commit` instead of the working tree. `file_path` is relative to the
+    /// repository root.
+    pub fn at_commit(
+        repo: &'repo Repository,
+        file_path: &Path,
+        commit: Oid,
+        config: &'repo BlameConfig,
+    ) -> Result<Self> {
+        let mut blame_opts = config.options();
+        blame_opts.newest_commit(commit);
         let blame = repo.blame_file(file_path, Some(&mut blame_opts))?;
 
-        Ok(FileBlameContext { repo, blame })
+        Ok(FileBlameContext {
+            repo,
+            hunks: hunks_of(&blame),
+            config,
+            modified: None,
+        })
     }
 }
 
@@ -101,6 +192,9 @@ impl<'a> Default for FileAnalysisConfig<'a> {
     fn default() -> Self {
         Self {
             repo: None,
+            blame_config: None,
+            blame_cache: None,
+            scan_cache: None,
             include_files: None,
         }
     }
@@ -110,6 +204,9 @@ impl<'a> FromRef<'a> for FileAnalysisConfig<'a> {
     fn from_ref(config: &'a Self) -> Self {
         Self {
             repo: config.repo,
+            blame_config: config.blame_config,
+            blame_cache: config.blame_cache,
+            scan_cache: config.scan_cache,
             include_files: config.include_files,
         }
     }
@@ -120,6 +217,7 @@ impl<'a> Default for DirAnalysisConfig<'a> {
         Self {
             file_analysis_config: FileAnalysisConfig::default(),
             exclude_dirs: None,
+            skip_nested_repos: false,
         }
     }
 }
@@ -129,6 +227,7 @@ impl<'a> FromRef<'a> for DirAnalysisConfig<'a> {
         Self {
             file_analysis_config: FileAnalysisConfig::from_ref(&config.file_analysis_config),
             exclude_dirs: config.exclude_dirs,
+            skip_nested_repos: config.skip_nested_repos,
         }
     }
 }
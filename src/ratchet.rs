@@ -0,0 +1,264 @@
+use crate::{
+    line_analyzer_types::BlameInfo,
+    path_analyzer_types::AnalysisResult,
+    ratchet_types::{Ratchet, RatchetChange, RatchetGroup, RatchetOutcome, TodoCounts},
+    utils::write_json,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+/// Used by `check --ratchet` unless another file is given.
+pub const DEFAULT_RATCHET_PATH: &str = ".todolint-ratchet.json";
+
+const RATCHET_VERSION: u32 = 1;
+
+/// Group name for todos without blame when grouping by owner.
+const UNKNOWN_OWNER: &str = "unknown";
+
+impl Ratchet {
+    pub fn new(group_by: RatchetGroup, counts: BTreeMap<String, TodoCounts>) -> Self {
+        Self {
+            version: RATCHET_VERSION,
+            group_by,
+            counts,
+        }
+    }
+}
+
+/// Valid and invalid todos of `analysis` per group.
+pub fn count_todos(
+    analysis: &AnalysisResult,
+    group_by: RatchetGroup,
+) -> BTreeMap<String, TodoCounts> {
+    let mut counts: BTreeMap<String, TodoCounts> = BTreeMap::new();
+
+    for file_analysis in analysis.file_analyses() {
+        let directory = file_analysis
+            .metadata
+            .stable_path()
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map_or_else(|| ".".to_string(), |parent| parent.display().to_string());
+
+        for valid in file_analysis.valids.iter().flatten() {
+            let group = match group_by {
+                RatchetGroup::Directory => directory.clone(),
+                RatchetGroup::Owner => owner(valid.blame_info.as_ref()),
+            };
+            counts.entry(group).or_default().valid += 1;
+        }
+        for invalid in file_analysis.invalids.iter().flatten() {
+            let group = match group_by {
+                RatchetGroup::Directory => directory.clone(),
+                RatchetGroup::Owner => owner(invalid.blame_info.as_ref()),
+            };
+            counts.entry(group).or_default().invalid += 1;
+        }
+    }
+
+    counts
+}
+
+fn owner(blame_info: Option<&BlameInfo>) -> String {
+    blame_info.map_or_else(
+        || UNKNOWN_OWNER.to_string(),
+        |blame_info| blame_info.author.email.to_string(),
+    )
+}
+
+/// Compares `counts` against the ratchet and lowers every count that dropped. Counts that
+/// increased are reported but left as they were. `scope` is the part of the project that was
+/// scanned, relative to its root, and empty if all of it was. Only directories under it are
+/// lowered, and owners only by a scan of the whole project, since other todos weren't counted.
+pub fn tighten_ratchet(
+    ratchet: &mut Ratchet,
+    counts: &BTreeMap<String, TodoCounts>,
+    scope: &Path,
+) -> RatchetOutcome {
+    let groups: BTreeSet<String> = ratchet
+        .counts
+        .keys()
+        .chain(counts.keys())
+        .cloned()
+        .collect();
+
+    let mut outcome = RatchetOutcome::default();
+    for group in groups {
+        let old = ratchet.counts.get(&group).copied().unwrap_or_default();
+        let new = counts.get(&group).copied().unwrap_or_default();
+        let in_scope = scope.as_os_str().is_empty()
+            || (ratchet.group_by == RatchetGroup::Directory
+                && Path::new(&group).starts_with(scope));
+
+        let pairs = [
+            ("valid", old.valid, new.valid),
+            ("invalid", old.invalid, new.invalid),
+        ];
+        for (kind, old, new) in pairs {
+            let change = RatchetChange {
+                group: group.clone(),
+                kind,
+                old,
+                new,
+            };
+            if new > old {
+                outcome.increased.push(change);
+            } else if new < old && in_scope {
+                outcome.decreased.push(change);
+            }
+        }
+        if !in_scope {
+            continue;
+        }
+
+        let tightened = TodoCounts {
+            valid: old.valid.min(new.valid),
+            invalid: old.invalid.min(new.invalid),
+        };
+        if tightened == TodoCounts::default() {
+            ratchet.counts.remove(&group);
+        } else {
+            ratchet.counts.insert(group, tightened);
+        }
+    }
+
+    outcome
+}
+
+pub fn load_ratchet(path: &Path) -> Result<Ratchet> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open ratchet '{}'", path.display()))?;
+    let ratchet: Ratchet = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to parse ratchet '{}'", path.display()))?;
+
+    if ratchet.version > RATCHET_VERSION {
+        anyhow::bail!(
+            "Ratchet has version {}, but only up to {} is supported",
+            ratchet.version,
+            RATCHET_VERSION
+        );
+    }
+    Ok(ratchet)
+}
+
+pub fn save_ratchet(ratchet: &Ratchet, path: &Path) -> Result<()> {
+    let mut file = File::create(path)
+        .with_context(|| format!("Failed to create ratchet '{}'", path.display()))?;
+    write_json(&mut file, ratchet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        path_analyzer_types::{DirAnalysisConfig, FileAnalysisConfig},
+        utils::{project_root, test_repo},
+    };
+    use git2::Repository;
+    use std::fs;
+
+    fn counts(entries: &[(&str, usize, usize)]) -> BTreeMap<String, TodoCounts> {
+        entries
+            .iter()
+            .map(|(group, valid, invalid)| {
+                (
+                    group.to_string(),
+                    TodoCounts {
+                        valid: *valid,
+                        invalid: *invalid,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_count_todos_by_directory() {
+        let analysis =
+            AnalysisResult::Directory(crate::analyze_dir(Path::new("./test"), &Default::default()));
+        let counts = count_todos(&analysis, RatchetGroup::Directory);
+
+        assert_eq!(counts.keys().collect::<Vec<_>>(), vec!["test"]);
+        assert!(counts["test"].valid > 0 && counts["test"].invalid > 0);
+    }
+
+    #[test]
+    fn test_count_todos_of_nested_repository() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("lib/l.rs", "// todo: l\n")], "first");
+        let inner = Repository::init(dir.path().join("vendor/inner"))?;
+        test_repo::commit(&inner, &[("lib/i.rs", "// todo: i\n")], "first");
+        assert_eq!(project_root(&inner)?, fs::canonicalize(dir.path())?);
+
+        // The nested repository's directories are the same groups whether it's scanned on its own
+        // or with the rest, and don't collide with the top-level repository's
+        for (scanned, groups) in [
+            (dir.path().to_path_buf(), vec!["lib", "vendor/inner/lib"]),
+            (dir.path().join("vendor/inner"), vec!["vendor/inner/lib"]),
+        ] {
+            let scanned_repo = Repository::discover(&scanned)?;
+            let config = DirAnalysisConfig {
+                file_analysis_config: FileAnalysisConfig {
+                    repo: Some(&scanned_repo),
+                    ..FileAnalysisConfig::default()
+                },
+                ..DirAnalysisConfig::default()
+            };
+            let mut analysis = AnalysisResult::Directory(crate::analyze_dir(&scanned, &config));
+            for file_analysis in analysis.file_analyses_mut() {
+                crate::set_project_path(&mut file_analysis.metadata, &project_root(&scanned_repo)?);
+            }
+
+            let counts = count_todos(&analysis, RatchetGroup::Directory);
+            assert_eq!(counts.keys().collect::<Vec<_>>(), groups);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tighten_ratchet() {
+        let mut ratchet = Ratchet::new(
+            RatchetGroup::Directory,
+            counts(&[("src", 3, 2), ("docs", 1, 0)]),
+        );
+        let outcome = tighten_ratchet(
+            &mut ratchet,
+            &counts(&[("src", 4, 1), ("new", 0, 1)]),
+            Path::new(""),
+        );
+
+        assert_eq!(
+            outcome
+                .increased
+                .iter()
+                .map(|change| (&change.group[..], change.kind))
+                .collect::<Vec<_>>(),
+            vec![("new", "invalid"), ("src", "valid")]
+        );
+        assert_eq!(outcome.decreased.len(), 2);
+        assert_eq!(ratchet.counts, counts(&[("src", 3, 1)]));
+    }
+
+    #[test]
+    fn test_tighten_ratchet_of_partial_scan() {
+        let mut ratchet = Ratchet::new(
+            RatchetGroup::Directory,
+            counts(&[("src/sub", 2, 0), ("src/sub/deep", 1, 0), ("docs", 1, 1)]),
+        );
+        let outcome = tighten_ratchet(
+            &mut ratchet,
+            &counts(&[("src/sub", 1, 0), ("docs", 2, 1)]),
+            Path::new("src/sub"),
+        );
+
+        // Groups outside the scan can still increase, but aren't lowered
+        assert_eq!(outcome.increased.len(), 1);
+        assert_eq!(outcome.decreased.len(), 2);
+        assert_eq!(ratchet.counts, counts(&[("src/sub", 1, 0), ("docs", 1, 1)]));
+    }
+}
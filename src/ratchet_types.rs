@@ -0,0 +1,44 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Highest todo counts allowed per group. Counts may only go down: `check --ratchet` fails when
+/// one increases, and lowers the stored count when one drops.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Ratchet {
+    pub version: u32,
+    pub group_by: RatchetGroup,
+    /// Groups without any todos are left out.
+    pub counts: BTreeMap<String, TodoCounts>,
+}
+
+#[derive(Serialize, Deserialize, ValueEnum, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RatchetGroup {
+    /// Directory containing the file, not including subdirectories.
+    Directory,
+    /// Email of the last author of the todo's line, per blame.
+    Owner,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TodoCounts {
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+/// A count that changed since the ratchet was last tightened.
+#[derive(Debug, PartialEq)]
+pub struct RatchetChange {
+    pub group: String,
+    pub kind: &'static str,
+    pub old: usize,
+    pub new: usize,
+}
+
+/// Result of comparing a scan against a ratchet.
+#[derive(Debug, Default)]
+pub struct RatchetOutcome {
+    pub increased: Vec<RatchetChange>,
+    pub decreased: Vec<RatchetChange>,
+}
@@ -0,0 +1,402 @@
+use crate::{
+    line_analyzer_types::{BlameInfo, CommentMarker, InvalidReason, InvalidTodoComment},
+    path_analyzer_types::{AnalysisResult, FileAnalysis},
+    schema::{check_schema_version, VersionedReport},
+    utils::write_json,
+};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+mod bson;
+mod checkstyle;
+mod delimited;
+mod github;
+mod gitlab;
+mod html;
+mod junit;
+mod markdown;
+mod ndjson;
+mod sarif;
+mod terminal;
+
+/// Turns an `AnalysisResult` into a specific output format.
+pub trait Reporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()>;
+}
+
+/// Reporter that writes output file by file while a scan is still running.
+pub trait StreamingReporter {
+    /// `path` is the scanned path, if known.
+    fn start(&mut self, path: Option<&Path>, writer: &mut dyn Write) -> Result<()>;
+    fn file(&mut self, file_analysis: &FileAnalysis, writer: &mut dyn Write) -> Result<()>;
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<()>;
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Terminal,
+    Json,
+    Sarif,
+    Checkstyle,
+    Junit,
+    Github,
+    Gitlab,
+    Csv,
+    Tsv,
+    Markdown,
+    Html,
+    Bson,
+    Ndjson,
+}
+
+/// Settings shared by all reporters; each reporter picks what applies to it.
+pub struct ReportOptions {
+    /// Enables colors and hyperlinks for formats meant to be read in a terminal.
+    pub styled: bool,
+    /// Columns for tabular formats.
+    pub columns: Vec<Column>,
+}
+
+/// A column of tabular output, one row per todo.
+#[derive(ValueEnum, EnumIter, Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Path,
+    Line,
+    Status,
+    Marker,
+    Delimiters,
+    Comment,
+    Email,
+    Timestamp,
+}
+
+/// Valid and invalid todos flattened into a single shape for tabular formats.
+pub struct TodoRow<'a> {
+    pub filepath: &'a Path,
+    pub line: usize,
+    /// `None` for valid todos.
+    pub reason: Option<InvalidReason>,
+    /// Delimiters with their enclosing characters, e.g. `(1)[2]`. Empty for invalid todos.
+    pub delimiters: String,
+    /// The comment for valid todos, the matched text for invalid ones.
+    pub comment: &'a str,
+    pub blame_info: Option<&'a BlameInfo>,
+}
+
+impl OutputFormat {
+    pub fn reporter(&self, options: ReportOptions) -> Box<dyn Reporter> {
+        match self {
+            OutputFormat::Terminal => Box::new(terminal::TerminalReporter {
+                styled: options.styled,
+            }),
+            OutputFormat::Json => Box::new(JsonReporter),
+            OutputFormat::Sarif => Box::new(sarif::SarifReporter),
+            OutputFormat::Checkstyle => Box::new(checkstyle::CheckstyleReporter),
+            OutputFormat::Junit => Box::new(junit::JunitReporter),
+            OutputFormat::Github => Box::new(github::GithubReporter),
+            OutputFormat::Gitlab => Box::new(gitlab::GitlabReporter),
+            OutputFormat::Csv => Box::new(delimited::DelimitedReporter {
+                separator: ',',
+                columns: options.columns,
+            }),
+            OutputFormat::Tsv => Box::new(delimited::DelimitedReporter {
+                separator: '\t',
+                columns: options.columns,
+            }),
+            OutputFormat::Markdown => Box::new(markdown::MarkdownReporter {
+                columns: options.columns,
+            }),
+            OutputFormat::Html => Box::new(html::HtmlReporter),
+            OutputFormat::Bson => Box::new(bson::BsonReporter),
+            OutputFormat::Ndjson => Box::new(ndjson::NdjsonReporter::default()),
+        }
+    }
+
+    /// Formats that can be written while scanning rather than after.
+    pub fn streaming_reporter(&self) -> Option<Box<dyn StreamingReporter>> {
+        match self {
+            OutputFormat::Ndjson => Some(Box::new(ndjson::NdjsonReporter::default())),
+            _ => None,
+        }
+    }
+}
+
+impl Column {
+    pub fn all() -> Vec<Column> {
+        Column::iter().collect()
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Line => "line",
+            Column::Status => "status",
+            Column::Marker => "marker",
+            Column::Delimiters => "delimiters",
+            Column::Comment => "comment",
+            Column::Email => "author_email",
+            Column::Timestamp => "timestamp",
+        }
+    }
+
+    pub fn value(&self, row: &TodoRow) -> String {
+        match self {
+            Column::Path => portable_path(row.filepath),
+            Column::Line => row.line.to_string(),
+            Column::Status => match row.reason {
+                Some(reason) => format!("invalid ({})", reason.code()),
+                None => "valid".to_string(),
+            },
+            Column::Marker => CommentMarker::Todo.to_string(),
+            Column::Delimiters => row.delimiters.clone(),
+            Column::Comment => row.comment.to_string(),
+            Column::Email => row
+                .blame_info
+                .map(|blame_info| blame_info.author.email.to_string())
+                .unwrap_or_default(),
+            Column::Timestamp => row
+                .blame_info
+                .map(|blame_info| blame_info.author.timestamp.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// All todos of the analysis, ordered by file and then by line.
+pub fn todo_rows(analysis: &AnalysisResult) -> Vec<TodoRow<'_>> {
+    analysis
+        .file_analyses()
+        .iter()
+        .flat_map(|file_analysis| {
+            let filepath = file_analysis.metadata.filepath.as_path();
+
+            let valids = file_analysis.valids.iter().flatten().map(|valid| TodoRow {
+                filepath,
+                line: valid.line,
+                reason: None,
+                delimiters: valid.line_info.delimiters_text(),
+                comment: &valid.line_info.comment,
+                blame_info: valid.blame_info.as_ref(),
+            });
+            let invalids = file_analysis
+                .invalids
+                .iter()
+                .flatten()
+                .map(|invalid| TodoRow {
+                    filepath,
+                    line: invalid.line,
+                    reason: Some(invalid.line_info.reason),
+                    delimiters: String::new(),
+                    comment: &invalid.line_info.full_text,
+                    blame_info: invalid.blame_info.as_ref(),
+                });
+
+            let mut rows: Vec<TodoRow> = valids.chain(invalids).collect();
+            rows.sort_by_key(|row| row.line);
+            rows
+        })
+        .collect()
+}
+
+/// The raw analysis, pretty-printed and tagged with the schema version.
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        write_json(writer, &VersionedReport::new(analysis))
+    }
+}
+
+/// Reads an analysis written by `JsonReporter`.
+pub fn load_json(reader: &mut dyn Read) -> Result<AnalysisResult> {
+    let value: serde_json::Value =
+        serde_json::from_reader(reader).context("File is not a JSON document")?;
+    let schema_version = value
+        .get("schema_version")
+        .and_then(|version| version.as_i64());
+    check_schema_version(schema_version)?;
+
+    let report: VersionedReport<AnalysisResult> =
+        serde_json::from_value(value).context("File is not a todolint JSON report")?;
+    Ok(report.analysis)
+}
+
+/// Loads a report previously written by the JSON or BSON reporter.
+pub fn load_report(path: &Path) -> Result<AnalysisResult> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    // A BSON document starts with its own length as a little-endian i32
+    let is_bson = bytes.len() >= 5
+        && bytes[..4]
+            .try_into()
+            .map(i32::from_le_bytes)
+            .is_ok_and(|length| length as usize == bytes.len());
+
+    if is_bson {
+        bson::load_bson(&mut bytes.as_slice())
+    } else {
+        load_json(&mut bytes.as_slice())
+    }
+}
+
+/// Path with forward slashes, as expected by URIs and most CI tools.
+pub fn portable_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Human readable explanation of why a todo is invalid, including the offending text.
+pub fn invalid_message(invalid: &InvalidTodoComment) -> String {
+    format!(
+        "{} Found `{}`.",
+        invalid.line_info.reason.description(),
+        invalid.line_info.full_text
+    )
+}
+
+/// Escapes text for use in XML attributes and element content.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_dir,
+        path_analyzer_types::{DirAnalysisConfig, FileAnalysisConfig},
+        set_project_path,
+        utils::{project_root, test_repo},
+    };
+    use git2::Repository;
+    use rstest::rstest;
+    use std::env;
+
+    /// The `test` fixtures, analyzed without blame. Shared by the reporters' tests.
+    pub fn test_analysis() -> AnalysisResult {
+        AnalysisResult::Directory(analyze_dir(
+            Path::new("test"),
+            &DirAnalysisConfig::default(),
+        ))
+    }
+
+    #[test]
+    fn test_json_round_trip() -> Result<()> {
+        let analysis = test_analysis();
+
+        let mut output = Vec::new();
+        JsonReporter.report(&analysis, &mut output)?;
+        let loaded = load_json(&mut output.as_slice())?;
+
+        assert_eq!(
+            serde_json::to_value(&loaded)?,
+            serde_json::to_value(&analysis)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_report_detects_format() -> Result<()> {
+        let analysis = test_analysis();
+
+        for format in [OutputFormat::Json, OutputFormat::Bson] {
+            let path = env::temp_dir().join(format!(
+                "todolint-load-report-{}-{:?}",
+                std::process::id(),
+                format
+            ));
+            let mut file = fs::File::create(&path)?;
+            format
+                .reporter(ReportOptions {
+                    styled: false,
+                    columns: Column::all(),
+                })
+                .report(&analysis, &mut file)?;
+
+            let loaded = load_report(&path);
+            fs::remove_file(&path)?;
+            assert_eq!(
+                loaded?.file_analyses().len(),
+                analysis.file_analyses().len()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ci_paths_of_nested_repository() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "")], "first");
+        let inner = Repository::init(dir.path().join("vendor/inner"))?;
+        test_repo::commit(&inner, &[("lib/i.rs", "// todo(0 1): i\n")], "first");
+
+        // Same path in the superproject whether the nested repository is scanned on its own or not
+        for scanned in [dir.path().to_path_buf(), dir.path().join("vendor/inner")] {
+            let scanned_repo = Repository::discover(&scanned)?;
+            let config = DirAnalysisConfig {
+                file_analysis_config: FileAnalysisConfig {
+                    repo: Some(&scanned_repo),
+                    ..FileAnalysisConfig::default()
+                },
+                ..DirAnalysisConfig::default()
+            };
+            let mut analysis = AnalysisResult::Directory(analyze_dir(&scanned, &config));
+            for file_analysis in analysis.file_analyses_mut() {
+                set_project_path(&mut file_analysis.metadata, &project_root(&scanned_repo)?);
+            }
+
+            for format in [
+                OutputFormat::Sarif,
+                OutputFormat::Checkstyle,
+                OutputFormat::Junit,
+                OutputFormat::Github,
+                OutputFormat::Gitlab,
+            ] {
+                let mut output = Vec::new();
+                format
+                    .reporter(ReportOptions {
+                        styled: false,
+                        columns: Column::all(),
+                    })
+                    .report(&analysis, &mut output)?;
+                let output = String::from_utf8(output)?;
+                assert!(
+                    output.contains("vendor/inner/lib/i.rs"),
+                    "{:?} output: {}",
+                    format,
+                    output
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(999, "only up to")]
+    #[case(1, "no longer supported")]
+    fn test_load_json_rejects_other_schema_versions(#[case] version: u32, #[case] error: &str) {
+        let report = format!(
+            r#"{{"schema_version": {}, "Directory": {{"total_files_scanned": 0, "last_scan_on": "2024-01-01T00:00:00Z", "file_analyses": []}}}}"#,
+            version
+        );
+        let result = load_json(&mut report.as_bytes());
+        assert!(result.unwrap_err().to_string().contains(error));
+    }
+}
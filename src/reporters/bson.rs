@@ -0,0 +1,124 @@
+use super::Reporter;
+use crate::{
+    path_analyzer_types::AnalysisResult,
+    schema::{check_schema_version, VersionedReport},
+};
+use anyhow::{Context, Result};
+use bson::{Bson, Document};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::io::{Read, Write};
+
+/// Fields holding a `DateTime<Utc>`. Serde writes them as RFC 3339 strings, which are stored as
+/// BSON datetimes instead so they can be queried as dates once ingested.
+const DATETIME_FIELDS: [&str; 3] = ["last_modified", "last_scan_on", "timestamp"];
+
+/// The analysis as a single BSON document, e.g. for ingestion into MongoDB.
+pub struct BsonReporter;
+
+impl Reporter for BsonReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let mut bson = bson::to_bson(&VersionedReport::new(analysis))
+            .context("Failed to serialize analysis to BSON")?;
+        strings_to_datetimes(&mut bson);
+
+        let document = match bson {
+            Bson::Document(document) => document,
+            _ => anyhow::bail!("Analysis did not serialize to a BSON document"),
+        };
+        document
+            .to_writer(writer)
+            .context("Failed to write BSON document")?;
+        Ok(())
+    }
+}
+
+/// Reads an analysis written by `BsonReporter`.
+pub fn load_bson(reader: &mut dyn Read) -> Result<AnalysisResult> {
+    let document = Document::from_reader(reader).context("Failed to read BSON document")?;
+    let schema_version = match document.get("schema_version") {
+        Some(Bson::Int32(version)) => Some(i64::from(*version)),
+        Some(Bson::Int64(version)) => Some(*version),
+        _ => None,
+    };
+    check_schema_version(schema_version)?;
+    let mut bson = Bson::Document(document);
+    datetimes_to_strings(&mut bson);
+
+    let report: VersionedReport<AnalysisResult> =
+        bson::from_bson(bson).context("BSON document is not a todolint analysis")?;
+    Ok(report.analysis)
+}
+
+fn strings_to_datetimes(bson: &mut Bson) {
+    for_each_datetime_field(bson, &mut |value| {
+        if let Bson::String(text) = value {
+            if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+                *value = Bson::DateTime(bson::DateTime::from_chrono(datetime.with_timezone(&Utc)));
+            }
+        }
+    });
+}
+
+fn datetimes_to_strings(bson: &mut Bson) {
+    for_each_datetime_field(bson, &mut |value| {
+        if let Bson::DateTime(datetime) = value {
+            *value = Bson::String(
+                datetime
+                    .to_chrono()
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            );
+        }
+    });
+}
+
+/// Calls `f` on the value of every field named in `DATETIME_FIELDS`, at any depth.
+fn for_each_datetime_field(bson: &mut Bson, f: &mut dyn FnMut(&mut Bson)) {
+    match bson {
+        Bson::Document(document) => {
+            for (key, value) in document.iter_mut() {
+                if DATETIME_FIELDS.contains(&key.as_str()) {
+                    f(value);
+                } else {
+                    for_each_datetime_field(value, f);
+                }
+            }
+        }
+        Bson::Array(values) => {
+            for value in values {
+                for_each_datetime_field(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reporters::tests::test_analysis, schema::SCHEMA_VERSION};
+
+    #[test]
+    fn test_bson_round_trip() -> Result<()> {
+        let analysis = test_analysis();
+
+        let mut output = Vec::new();
+        BsonReporter.report(&analysis, &mut output)?;
+
+        let document = Document::from_reader(&mut output.as_slice())?;
+        assert_eq!(document.get_i64("schema_version")?, SCHEMA_VERSION as i64);
+        let directory = document.get_document("Directory")?;
+        assert!(directory.get_datetime("last_scan_on").is_ok());
+        let first_file = directory.get_array("file_analyses")?[0]
+            .as_document()
+            .unwrap();
+        assert!(first_file
+            .get_document("metadata")?
+            .get_datetime("last_modified")
+            .is_ok());
+
+        let loaded = load_bson(&mut output.as_slice())?;
+        assert_eq!(loaded.file_analyses().len(), analysis.file_analyses().len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use super::{escape_xml, invalid_message, portable_path, Reporter};
+use crate::path_analyzer_types::AnalysisResult;
+use anyhow::Result;
+use std::io::Write;
+
+/// Checkstyle XML, one `<file>` per scanned file and one `<error>` per invalid todo.
+pub struct CheckstyleReporter;
+
+impl Reporter for CheckstyleReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<checkstyle version="4.3">"#)?;
+
+        for file_analysis in analysis.file_analyses() {
+            let filepath = portable_path(&file_analysis.metadata.stable_path());
+            writeln!(writer, r#"  <file name="{}">"#, escape_xml(&filepath))?;
+
+            for invalid in file_analysis.invalids.iter().flatten() {
+                writeln!(
+                    writer,
+                    r#"    <error line="{}" column="{}" severity="warning" message="{}" source="todolint.{}"/>"#,
+                    invalid.line,
+                    invalid.column,
+                    escape_xml(&invalid_message(invalid)),
+                    invalid.line_info.reason.code(),
+                )?;
+            }
+
+            writeln!(writer, "  </file>")?;
+        }
+
+        writeln!(writer, "</checkstyle>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporters::tests::test_analysis;
+
+    #[test]
+    fn test_checkstyle_errors_match_invalids() -> Result<()> {
+        let analysis = test_analysis();
+        let invalid_count: usize = analysis
+            .file_analyses()
+            .iter()
+            .map(|file_analysis| file_analysis.invalids.as_ref().map_or(0, Vec::len))
+            .sum();
+
+        let mut output = Vec::new();
+        CheckstyleReporter.report(&analysis, &mut output)?;
+        let xml = String::from_utf8(output)?;
+
+        assert_eq!(
+            xml.matches("<file ").count(),
+            analysis.file_analyses().len()
+        );
+        assert_eq!(xml.matches("<error ").count(), invalid_count);
+        assert!(xml.contains(r#"<file name="test/invalid.txt">"#));
+        assert!(xml.contains(r#"<error line="2" column="1" severity="warning""#));
+        assert!(xml.contains(r#"source="todolint.TL002"/>"#));
+
+        Ok(())
+    }
+}
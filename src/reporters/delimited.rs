@@ -0,0 +1,68 @@
+use super::{todo_rows, Column, Reporter};
+use crate::path_analyzer_types::AnalysisResult;
+use anyhow::Result;
+use std::io::Write;
+
+/// CSV or TSV, with a header row and one row per todo.
+pub struct DelimitedReporter {
+    pub separator: char,
+    pub columns: Vec<Column>,
+}
+
+impl Reporter for DelimitedReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let headers: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| self.escape(column.header()))
+            .collect();
+        writeln!(writer, "{}", headers.join(&self.separator.to_string()))?;
+
+        for row in todo_rows(analysis) {
+            let cells: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| self.escape(&column.value(&row)))
+                .collect();
+            writeln!(writer, "{}", cells.join(&self.separator.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DelimitedReporter {
+    /// Quotes CSV fields as per RFC 4180. TSV has no quoting, so separators and line breaks are
+    /// replaced with spaces instead.
+    fn escape(&self, value: &str) -> String {
+        if self.separator == '\t' {
+            return value.replace(['\t', '\r', '\n'], " ");
+        }
+
+        if value.contains([self.separator, '"', '\r', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(',', "plain", "plain")]
+    #[case(',', "a, b", "\"a, b\"")]
+    #[case(',', "say \"hi\"", "\"say \"\"hi\"\"\"")]
+    #[case('\t', "a\tb\nc", "a b c")]
+    #[case('\t', "a, \"b\"", "a, \"b\"")]
+    fn test_escape(#[case] separator: char, #[case] value: &str, #[case] expected: &str) {
+        let reporter = DelimitedReporter {
+            separator,
+            columns: Column::all(),
+        };
+        assert_eq!(reporter.escape(value), expected);
+    }
+}
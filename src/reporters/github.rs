@@ -0,0 +1,105 @@
+use super::{invalid_message, portable_path, Reporter};
+use crate::path_analyzer_types::AnalysisResult;
+use anyhow::Result;
+use std::io::Write;
+
+/// GitHub Actions workflow commands. Invalid todos become `::warning` annotations, valid ones
+/// `::notice` annotations, so both show up inline on pull request diffs.
+pub struct GithubReporter;
+
+impl Reporter for GithubReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        for file_analysis in analysis.file_analyses() {
+            let filepath = escape_property(&portable_path(&file_analysis.metadata.stable_path()));
+
+            for valid in file_analysis.valids.iter().flatten() {
+                writeln!(
+                    writer,
+                    "::notice file={},line={},col={},title=todo::{}",
+                    filepath,
+                    valid.line,
+                    valid.column,
+                    escape_data(&valid.line_info.comment)
+                )?;
+            }
+
+            for invalid in file_analysis.invalids.iter().flatten() {
+                writeln!(
+                    writer,
+                    "::warning file={},line={},col={},title={}::{}",
+                    filepath,
+                    invalid.line,
+                    invalid.column,
+                    escape_property(&format!(
+                        "{} {}",
+                        invalid.line_info.reason.code(),
+                        invalid.line_info.reason.name()
+                    )),
+                    escape_data(&invalid_message(invalid))
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Escapes the message part of a workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a `key=value` parameter of a workflow command.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyze_lines, path_analyzer_types::FileMetadata};
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_github_annotations() -> Result<()> {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from("src/a:b,c%d\ne.rs"),
+            repo_path: None,
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        let content = b"// todo: fine\n// todo(0 1): bad\n";
+        let analysis = AnalysisResult::File(analyze_lines(metadata, &content[..], None)?);
+
+        let mut output = Vec::new();
+        GithubReporter.report(&analysis, &mut output)?;
+        let output = String::from_utf8(output)?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "::notice file=src/a%3Ab%2Cc%25d%0Ae.rs,line=1,col=1,title=todo::fine"
+        );
+        assert!(lines[1].starts_with(
+            "::warning file=src/a%3Ab%2Cc%25d%0Ae.rs,line=2,col=1,title=TL002 invalid-delimiter-content::"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_github_escaping() {
+        assert_eq!(
+            escape_data("50% done\r\nnext: a, b"),
+            "50%25 done%0D%0Anext: a, b"
+        );
+        assert_eq!(
+            escape_property("50% done\r\nnext: a, b"),
+            "50%25 done%0D%0Anext%3A a%2C b"
+        );
+    }
+}
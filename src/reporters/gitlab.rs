@@ -0,0 +1,126 @@
+use super::{invalid_message, portable_path, Reporter};
+use crate::{path_analyzer_types::AnalysisResult, utils::write_json};
+use anyhow::Result;
+use serde::Serialize;
+use std::{collections::HashMap, io::Write};
+
+/// GitLab Code Quality report. Fingerprints are derived from the todo's content rather than its
+/// line number, so GitLab can tell moved todos apart from new ones.
+pub struct GitlabReporter;
+
+#[derive(Serialize)]
+struct Issue {
+    description: String,
+    check_name: String,
+    fingerprint: String,
+    severity: &'static str,
+    location: Location,
+}
+
+#[derive(Serialize)]
+struct Location {
+    path: String,
+    lines: Lines,
+}
+
+#[derive(Serialize)]
+struct Lines {
+    begin: usize,
+}
+
+impl Reporter for GitlabReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let mut issues = Vec::new();
+        // Identical todos in the same file share a fingerprint; GitLab expects them to be unique
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut unique = |fingerprint: String| {
+            let count = occurrences.entry(fingerprint.clone()).or_default();
+            *count += 1;
+            match *count {
+                1 => fingerprint,
+                n => format!("{}-{}", fingerprint, n),
+            }
+        };
+
+        for file_analysis in analysis.file_analyses() {
+            let filepath = file_analysis.metadata.stable_path();
+
+            for valid in file_analysis.valids.iter().flatten() {
+                issues.push(Issue {
+                    description: format!("todo: {}", valid.line_info.comment),
+                    check_name: "todo".to_string(),
+                    fingerprint: unique(valid.fingerprint(&filepath)),
+                    severity: "info",
+                    location: Location {
+                        path: portable_path(&filepath),
+                        lines: Lines { begin: valid.line },
+                    },
+                });
+            }
+
+            for invalid in file_analysis.invalids.iter().flatten() {
+                issues.push(Issue {
+                    description: invalid_message(invalid),
+                    check_name: invalid.line_info.reason.code().to_string(),
+                    fingerprint: unique(invalid.fingerprint(&filepath)),
+                    severity: "minor",
+                    location: Location {
+                        path: portable_path(&filepath),
+                        lines: Lines {
+                            begin: invalid.line,
+                        },
+                    },
+                });
+            }
+        }
+
+        write_json(writer, &issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_lines, path_analyzer_types::FileMetadata, reporters::tests::test_analysis,
+    };
+    use chrono::Utc;
+    use std::{collections::HashSet, path::PathBuf};
+
+    #[test]
+    fn test_gitlab_fingerprints_are_unique() -> Result<()> {
+        let analysis = test_analysis();
+
+        let mut output = Vec::new();
+        GitlabReporter.report(&analysis, &mut output)?;
+        let issues: Vec<serde_json::Value> = serde_json::from_slice(&output)?;
+
+        let fingerprints: HashSet<_> = issues
+            .iter()
+            .map(|issue| issue["fingerprint"].as_str().unwrap())
+            .collect();
+        assert!(!issues.is_empty());
+        assert_eq!(fingerprints.len(), issues.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gitlab_paths_are_repository_relative() -> Result<()> {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from("../project/src/lib.rs"),
+            repo_path: Some(PathBuf::from("src/lib.rs")),
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        let analysis = AnalysisResult::File(analyze_lines(metadata, &b"// todo: a\n"[..], None)?);
+
+        let mut output = Vec::new();
+        GitlabReporter.report(&analysis, &mut output)?;
+        let issues: Vec<serde_json::Value> = serde_json::from_slice(&output)?;
+
+        assert_eq!(issues[0]["location"]["path"], "src/lib.rs");
+        Ok(())
+    }
+}
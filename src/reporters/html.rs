@@ -0,0 +1,380 @@
+use super::{escape_xml, portable_path, todo_rows, Column, Reporter, TodoRow};
+use crate::path_analyzer_types::AnalysisResult;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::Component,
+};
+
+/// A single, self-contained HTML page: todo table, charts and directory tree. Nothing is loaded
+/// from the network, so the file can be archived as is.
+pub struct HtmlReporter;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+h1 { margin-bottom: 0.25rem; }
+.summary { color: #555; margin-top: 0; }
+.charts { display: flex; flex-wrap: wrap; gap: 2rem; }
+.chart h3 { margin: 0 0 0.5rem; font-size: 1rem; }
+.chart text { font-size: 12px; }
+.tree ul { list-style: none; padding-left: 1.25rem; margin: 0; }
+.tree summary { cursor: pointer; }
+.count { color: #555; font-size: 0.85em; }
+.count .invalid { color: #b45309; }
+.controls { margin: 1rem 0; display: flex; gap: 1rem; align-items: center; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9rem; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3rem 0.5rem; text-align: left; vertical-align: top; }
+th { cursor: pointer; background: #f5f5f5; position: sticky; top: 0; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+tr.invalid { background: #fef3c7; }
+td.reason { color: #b45309; }
+code { font-family: ui-monospace, monospace; }
+"#;
+
+const SCRIPT: &str = r#"
+const table = document.getElementById("todos");
+const rows = Array.from(table.tBodies[0].rows);
+const filter = document.getElementById("filter");
+const invalidOnly = document.getElementById("invalid-only");
+
+function applyFilter() {
+  const needle = filter.value.toLowerCase();
+  for (const row of rows) {
+    const matches = row.textContent.toLowerCase().includes(needle);
+    const shown = matches && (!invalidOnly.checked || row.classList.contains("invalid"));
+    row.hidden = !shown;
+  }
+}
+filter.addEventListener("input", applyFilter);
+invalidOnly.addEventListener("change", applyFilter);
+
+for (const [index, header] of Array.from(table.tHead.rows[0].cells).entries()) {
+  header.addEventListener("click", () => {
+    const order = header.dataset.order === "asc" ? "desc" : "asc";
+    for (const other of table.tHead.rows[0].cells) delete other.dataset.order;
+    header.dataset.order = order;
+    const key = (row) => row.cells[index].dataset.sort ?? row.cells[index].textContent;
+    rows.sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const cmp = !isNaN(x) && !isNaN(y) && x !== "" && y !== ""
+        ? Number(x) - Number(y)
+        : x.localeCompare(y);
+      return order === "asc" ? cmp : -cmp;
+    });
+    table.tBodies[0].append(...rows);
+  });
+}
+"#;
+
+/// Counts of valid and invalid todos below a directory, or within a file.
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+    valid: usize,
+    invalid: usize,
+}
+
+impl Reporter for HtmlReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let rows = todo_rows(analysis);
+        let invalid_count = rows.iter().filter(|row| row.reason.is_some()).count();
+        let now = Utc::now();
+
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, r#"<html lang="en">"#)?;
+        writeln!(writer, "<head>")?;
+        writeln!(writer, r#"<meta charset="utf-8">"#)?;
+        writeln!(writer, "<title>todolint report</title>")?;
+        writeln!(writer, "<style>{}</style>", STYLE)?;
+        writeln!(writer, "</head>")?;
+        writeln!(writer, "<body>")?;
+
+        writeln!(writer, "<h1>todolint report</h1>")?;
+        writeln!(
+            writer,
+            r#"<p class="summary">{} todo(s) in {} file(s), {} invalid. Generated {}.</p>"#,
+            rows.len(),
+            analysis.file_analyses().len(),
+            invalid_count,
+            now.format("%Y-%m-%d %H:%M UTC")
+        )?;
+
+        writeln!(writer, "<h2>Overview</h2>")?;
+        writeln!(writer, r#"<div class="charts">"#)?;
+        writeln!(writer, "{}", bar_chart("By author", &by_author(&rows)))?;
+        writeln!(writer, "{}", bar_chart("By age", &by_age(&rows, now)))?;
+        writeln!(
+            writer,
+            "{}",
+            bar_chart("By directory", &by_directory(&rows))
+        )?;
+        writeln!(writer, "</div>")?;
+
+        writeln!(writer, "<h2>Directories</h2>")?;
+        writeln!(writer, r#"<div class="tree">"#)?;
+        write_tree(writer, ".", &build_tree(analysis))?;
+        writeln!(writer, "</div>")?;
+
+        writeln!(writer, "<h2>Todos</h2>")?;
+        write_table(writer, &rows)?;
+
+        writeln!(writer, "<script>{}</script>", SCRIPT)?;
+        writeln!(writer, "</body>")?;
+        writeln!(writer, "</html>")?;
+        Ok(())
+    }
+}
+
+fn write_table(writer: &mut dyn Write, rows: &[TodoRow]) -> Result<()> {
+    writeln!(writer, r#"<div class="controls">"#)?;
+    writeln!(
+        writer,
+        r#"<input id="filter" type="search" placeholder="Filter todos" size="40">"#
+    )?;
+    writeln!(
+        writer,
+        r#"<label><input id="invalid-only" type="checkbox"> Invalid only</label>"#
+    )?;
+    writeln!(writer, "</div>")?;
+
+    writeln!(writer, r#"<table id="todos">"#)?;
+    writeln!(
+        writer,
+        "<thead><tr><th>Path</th><th>Line</th><th>Status</th><th>Delimiters</th><th>Comment</th><th>Author</th><th>Date</th><th>Reason</th></tr></thead>"
+    )?;
+    writeln!(writer, "<tbody>")?;
+    for row in rows {
+        let timestamp = row.blame_info.map(|blame_info| blame_info.author.timestamp);
+        writeln!(
+            writer,
+            r#"<tr{}><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td><td><code>{}</code></td><td>{}</td><td data-sort="{}">{}</td><td class="reason">{}</td></tr>"#,
+            if row.reason.is_some() {
+                r#" class="invalid""#
+            } else {
+                ""
+            },
+            escape_xml(&Column::Path.value(row)),
+            row.line,
+            escape_xml(&Column::Status.value(row)),
+            escape_xml(&row.delimiters),
+            escape_xml(row.comment),
+            escape_xml(&Column::Email.value(row)),
+            timestamp.map_or(0, |timestamp| timestamp.timestamp()),
+            timestamp
+                .map(|timestamp| timestamp.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            row.reason
+                .map(|reason| escape_xml(reason.description()))
+                .unwrap_or_default(),
+        )?;
+    }
+    writeln!(writer, "</tbody>")?;
+    writeln!(writer, "</table>")?;
+    Ok(())
+}
+
+fn build_tree(analysis: &AnalysisResult) -> TreeNode {
+    let mut root = TreeNode::default();
+
+    for file_analysis in analysis.file_analyses() {
+        let valid = file_analysis.valids.as_ref().map_or(0, Vec::len);
+        let invalid = file_analysis.invalids.as_ref().map_or(0, Vec::len);
+
+        let mut node = &mut root;
+        node.valid += valid;
+        node.invalid += invalid;
+        for component in file_analysis.metadata.filepath.components() {
+            if let Component::Normal(name) = component {
+                node = node
+                    .children
+                    .entry(name.to_string_lossy().to_string())
+                    .or_default();
+                node.valid += valid;
+                node.invalid += invalid;
+            }
+        }
+    }
+
+    root
+}
+
+fn write_tree(writer: &mut dyn Write, name: &str, node: &TreeNode) -> Result<()> {
+    let label = format!(
+        r#"{} <span class="count">({} valid, <span class="invalid">{} invalid</span>)</span>"#,
+        escape_xml(name),
+        node.valid,
+        node.invalid
+    );
+
+    if node.children.is_empty() {
+        writeln!(writer, "<li>{}</li>", label)?;
+        return Ok(());
+    }
+
+    writeln!(writer, "<details open><summary>{}</summary><ul>", label)?;
+    for (child_name, child) in &node.children {
+        write_tree(writer, child_name, child)?;
+    }
+    writeln!(writer, "</ul></details>")?;
+    Ok(())
+}
+
+/// Horizontal bar chart as inline SVG.
+fn bar_chart(title: &str, data: &[(String, usize)]) -> String {
+    const BAR_HEIGHT: usize = 18;
+    const LABEL_WIDTH: usize = 180;
+    const BAR_WIDTH: usize = 240;
+
+    let max = data
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let height = (data.len().max(1)) * (BAR_HEIGHT + 4);
+
+    let mut svg = format!(
+        r#"<div class="chart"><h3>{}</h3><svg width="{}" height="{}" role="img">"#,
+        escape_xml(title),
+        LABEL_WIDTH + BAR_WIDTH + 50,
+        height
+    );
+    for (index, (label, count)) in data.iter().enumerate() {
+        let y = index * (BAR_HEIGHT + 4);
+        let width = count * BAR_WIDTH / max;
+        svg.push_str(&format!(
+            r##"<text x="{}" y="{}" text-anchor="end">{}</text><rect x="{}" y="{}" width="{}" height="{}" fill="#3b82f6"><title>{}: {}</title></rect><text x="{}" y="{}">{}</text>"##,
+            LABEL_WIDTH - 6,
+            y + BAR_HEIGHT - 5,
+            escape_xml(&truncate(label, 28)),
+            LABEL_WIDTH,
+            y,
+            width,
+            BAR_HEIGHT,
+            escape_xml(label),
+            count,
+            LABEL_WIDTH + width + 4,
+            y + BAR_HEIGHT - 5,
+            count
+        ));
+    }
+    if data.is_empty() {
+        svg.push_str(r#"<text x="0" y="14">No data</text>"#);
+    }
+    svg.push_str("</svg></div>");
+    svg
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_chars - 1).collect();
+        format!("{}…", truncated)
+    }
+}
+
+/// Counts per key, largest first.
+fn sorted_counts(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+fn by_author(rows: &[TodoRow]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::new();
+    for row in rows {
+        let author = row
+            .blame_info
+            .map(|blame_info| blame_info.author.email.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        *counts.entry(author).or_default() += 1;
+    }
+    sorted_counts(counts)
+}
+
+fn by_directory(rows: &[TodoRow]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::new();
+    for row in rows {
+        let directory = row
+            .filepath
+            .parent()
+            .map(portable_path)
+            .filter(|directory| !directory.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        *counts.entry(directory).or_default() += 1;
+    }
+    sorted_counts(counts)
+}
+
+/// Counts per age bucket, in bucket order rather than by size.
+fn by_age(rows: &[TodoRow], now: DateTime<Utc>) -> Vec<(String, usize)> {
+    const BUCKETS: [(&str, i64); 5] = [
+        ("< 1 week", 7),
+        ("< 1 month", 30),
+        ("< 3 months", 90),
+        ("< 1 year", 365),
+        ("1 year +", i64::MAX),
+    ];
+
+    let mut counts = vec![0; BUCKETS.len() + 1];
+    for row in rows {
+        let index = match row.blame_info {
+            Some(blame_info) => {
+                let days = (now - blame_info.author.timestamp).num_days();
+                BUCKETS
+                    .iter()
+                    .position(|(_, max_days)| days < *max_days)
+                    .unwrap_or(BUCKETS.len() - 1)
+            }
+            None => BUCKETS.len(),
+        };
+        counts[index] += 1;
+    }
+
+    BUCKETS
+        .iter()
+        .map(|(label, _)| label.to_string())
+        .chain(["unknown".to_string()])
+        .zip(counts)
+        .filter(|(label, count)| *count > 0 || label != "unknown")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporters::tests::test_analysis;
+
+    #[test]
+    fn test_tree_counts_roll_up() {
+        let analysis = test_analysis();
+        let tree = build_tree(&analysis);
+
+        let test_dir = &tree.children["test"];
+        assert_eq!(test_dir.children.len(), analysis.file_analyses().len());
+        assert_eq!(
+            test_dir.valid,
+            test_dir
+                .children
+                .values()
+                .map(|file| file.valid)
+                .sum::<usize>()
+        );
+        assert_eq!(
+            test_dir.invalid,
+            test_dir
+                .children
+                .values()
+                .map(|file| file.invalid)
+                .sum::<usize>()
+        );
+        assert_eq!(
+            (tree.valid, tree.invalid),
+            (test_dir.valid, test_dir.invalid)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use super::{escape_xml, invalid_message, portable_path, Reporter};
+use crate::path_analyzer_types::{AnalysisResult, FileAnalysis};
+use anyhow::Result;
+use std::io::Write;
+
+/// JUnit XML, one `<testsuite>` per scanned file. Every todo is a test case; invalid todos are
+/// failures.
+pub struct JunitReporter;
+
+impl Reporter for JunitReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let file_analyses = analysis.file_analyses();
+        let (tests, failures) = file_analyses
+            .iter()
+            .map(counts)
+            .fold((0, 0), |(tests, failures), (t, f)| {
+                (tests + t, failures + f)
+            });
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<testsuites name="todolint" tests="{}" failures="{}">"#,
+            tests, failures
+        )?;
+
+        for file_analysis in file_analyses {
+            let filepath = escape_xml(&portable_path(&file_analysis.metadata.stable_path()));
+            let (tests, failures) = counts(file_analysis);
+            writeln!(
+                writer,
+                r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+                filepath, tests, failures
+            )?;
+
+            for valid in file_analysis.valids.iter().flatten() {
+                writeln!(
+                    writer,
+                    r#"    <testcase name="{}:{}" classname="{}"/>"#,
+                    filepath, valid.line, filepath
+                )?;
+            }
+
+            for invalid in file_analysis.invalids.iter().flatten() {
+                writeln!(
+                    writer,
+                    r#"    <testcase name="{}:{}" classname="{}">"#,
+                    filepath, invalid.line, filepath
+                )?;
+                writeln!(
+                    writer,
+                    r#"      <failure message="{}" type="{}">{}:{}:{}</failure>"#,
+                    escape_xml(&invalid_message(invalid)),
+                    invalid.line_info.reason.code(),
+                    filepath,
+                    invalid.line,
+                    invalid.column,
+                )?;
+                writeln!(writer, "    </testcase>")?;
+            }
+
+            writeln!(writer, "  </testsuite>")?;
+        }
+
+        writeln!(writer, "</testsuites>")?;
+        Ok(())
+    }
+}
+
+/// (tests, failures) for a single file.
+fn counts(file_analysis: &FileAnalysis) -> (usize, usize) {
+    let valids = file_analysis.valids.as_ref().map_or(0, Vec::len);
+    let invalids = file_analysis.invalids.as_ref().map_or(0, Vec::len);
+    (valids + invalids, invalids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporters::tests::test_analysis;
+
+    #[test]
+    fn test_junit_failures_match_invalids() -> Result<()> {
+        let analysis = test_analysis();
+        let invalid_count: usize = analysis
+            .file_analyses()
+            .iter()
+            .map(|file_analysis| counts(file_analysis).1)
+            .sum();
+
+        let mut output = Vec::new();
+        JunitReporter.report(&analysis, &mut output)?;
+        let xml = String::from_utf8(output)?;
+
+        assert_eq!(
+            xml.matches("<testsuite ").count(),
+            analysis.file_analyses().len()
+        );
+        assert_eq!(xml.matches("<failure ").count(), invalid_count);
+        assert!(xml.contains(&format!(r#"failures="{}">"#, invalid_count)));
+
+        Ok(())
+    }
+}
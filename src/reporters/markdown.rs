@@ -0,0 +1,148 @@
+use super::{portable_path, todo_rows, Column, Reporter, TodoRow};
+use crate::path_analyzer_types::AnalysisResult;
+use anyhow::Result;
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+/// Markdown with one table per directory. Paths link to the todo's line, relative to where the
+/// scan was run.
+pub struct MarkdownReporter {
+    pub columns: Vec<Column>,
+}
+
+impl Reporter for MarkdownReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let rows = todo_rows(analysis);
+
+        let mut directories: BTreeMap<String, Vec<&TodoRow>> = BTreeMap::new();
+        for row in &rows {
+            let directory = row
+                .filepath
+                .parent()
+                .map(portable_path)
+                .filter(|directory| !directory.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            directories.entry(directory).or_default().push(row);
+        }
+
+        writeln!(writer, "# Todos")?;
+        writeln!(writer)?;
+        let invalid_count = rows.iter().filter(|row| row.reason.is_some()).count();
+        writeln!(
+            writer,
+            "{} todo(s) in {} file(s), {} invalid.",
+            rows.len(),
+            analysis.file_analyses().len(),
+            invalid_count
+        )?;
+
+        for (directory, rows) in directories {
+            writeln!(writer)?;
+            writeln!(writer, "## `{}`", directory)?;
+            writeln!(writer)?;
+
+            let headers: Vec<&str> = self.columns.iter().map(|column| column.header()).collect();
+            writeln!(writer, "| {} |", headers.join(" | "))?;
+            writeln!(writer, "|{}", " --- |".repeat(headers.len()))?;
+
+            for row in rows {
+                let cells: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| match column {
+                        Column::Path => link(row.filepath, row.line),
+                        _ => escape_cell(&column.value(row)),
+                    })
+                    .collect();
+                writeln!(writer, "| {} |", cells.join(" | "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Link to a line of a file, labelled with the file name.
+fn link(filepath: &Path, line: usize) -> String {
+    let name = filepath
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| portable_path(filepath));
+    format!(
+        "[{}:{}]({}#L{})",
+        escape_cell(&name),
+        line,
+        portable_path(filepath).replace(' ', "%20"),
+        line
+    )
+}
+
+/// Keeps cell content from breaking the table or being rendered as markup.
+fn escape_cell(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' | '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_lines,
+        path_analyzer_types::{DirectoryAnalysis, FileAnalysis, FileMetadata},
+    };
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    fn file_analysis(filepath: &str, content: &str) -> Result<FileAnalysis> {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from(filepath),
+            repo_path: None,
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        analyze_lines(metadata, content.as_bytes(), None)
+    }
+
+    #[test]
+    fn test_markdown_tables_per_directory() -> Result<()> {
+        let analysis = AnalysisResult::Directory(DirectoryAnalysis {
+            total_files_scanned: 2,
+            last_scan_on: Utc::now(),
+            file_analyses: vec![
+                file_analysis("src/my lib.rs", "// todo: a | b\n")?,
+                file_analysis("docs/x.rs", "\n// todo(0 1): c\n")?,
+            ],
+        });
+
+        let mut output = Vec::new();
+        MarkdownReporter {
+            columns: vec![Column::Path, Column::Comment],
+        }
+        .report(&analysis, &mut output)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.contains("2 todo(s) in 2 file(s), 1 invalid."));
+        let docs = output.find("## `docs`").unwrap();
+        let src = output.find("## `src`").unwrap();
+        assert!(docs < src);
+        assert!(output.contains("| [x.rs:2](docs/x.rs#L2) | // todo(0 1): c |"));
+        assert!(output.contains("| [my lib.rs:1](src/my%20lib.rs#L1) | a \\| b |"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_markdown_escape_cell() {
+        assert_eq!(escape_cell("a|b\r\nc"), "a\\|b  c");
+        assert_eq!(escape_cell("*[x]*"), "\\*\\[x\\]\\*");
+    }
+}
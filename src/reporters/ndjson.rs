@@ -0,0 +1,165 @@
+use super::{Reporter, StreamingReporter};
+use crate::{
+    line_analyzer_types::{InvalidTodoComment, ValidTodoComment},
+    path_analyzer_types::{AnalysisResult, FileAnalysis},
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{io::Write, path::Path};
+
+/// Newline-delimited JSON: a header record, one record per todo and a footer record with totals.
+/// Todo records are written as soon as their file has been analyzed.
+#[derive(Default)]
+pub struct NdjsonReporter {
+    files_scanned: usize,
+    valid: usize,
+    invalid: usize,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Header {
+        tool: &'static str,
+        version: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<&'a Path>,
+        started_at: DateTime<Utc>,
+    },
+    Todo(TodoRecord<'a>),
+    Footer {
+        files_scanned: usize,
+        valid: usize,
+        invalid: usize,
+        finished_at: DateTime<Utc>,
+    },
+}
+
+#[derive(Serialize)]
+struct TodoRecord<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    todo: Todo<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum Todo<'a> {
+    Valid(&'a ValidTodoComment),
+    Invalid(&'a InvalidTodoComment),
+}
+
+impl Todo<'_> {
+    fn line(&self) -> usize {
+        match self {
+            Todo::Valid(valid) => valid.line,
+            Todo::Invalid(invalid) => invalid.line,
+        }
+    }
+}
+
+impl StreamingReporter for NdjsonReporter {
+    fn start(&mut self, path: Option<&Path>, writer: &mut dyn Write) -> Result<()> {
+        write_record(
+            writer,
+            &Record::Header {
+                tool: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                path,
+                started_at: Utc::now(),
+            },
+        )
+    }
+
+    fn file(&mut self, file_analysis: &FileAnalysis, writer: &mut dyn Write) -> Result<()> {
+        let valids = file_analysis.valids.iter().flatten().map(Todo::Valid);
+        let invalids = file_analysis.invalids.iter().flatten().map(Todo::Invalid);
+        let mut todos: Vec<Todo> = valids.chain(invalids).collect();
+        todos.sort_by_key(Todo::line);
+
+        self.files_scanned += 1;
+        for todo in todos {
+            match todo {
+                Todo::Valid(_) => self.valid += 1,
+                Todo::Invalid(_) => self.invalid += 1,
+            }
+            write_record(
+                writer,
+                &Record::Todo(TodoRecord {
+                    path: &file_analysis.metadata.filepath,
+                    todo,
+                }),
+            )?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<()> {
+        write_record(
+            writer,
+            &Record::Footer {
+                files_scanned: self.files_scanned,
+                valid: self.valid,
+                invalid: self.invalid,
+                finished_at: Utc::now(),
+            },
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Reporter for NdjsonReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let mut reporter = NdjsonReporter::default();
+        reporter.start(None, writer)?;
+        for file_analysis in analysis.file_analyses() {
+            reporter.file(file_analysis, writer)?;
+        }
+        reporter.finish(writer)
+    }
+}
+
+fn write_record(writer: &mut dyn Write, record: &Record) -> Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporters::tests::test_analysis;
+
+    #[test]
+    fn test_ndjson_one_record_per_line() -> Result<()> {
+        let analysis = test_analysis();
+
+        let mut output = Vec::new();
+        NdjsonReporter::default().report(&analysis, &mut output)?;
+        let records: Vec<serde_json::Value> = String::from_utf8(output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+
+        let todo_count = records.len() - 2;
+        assert_eq!(records[0]["type"], "header");
+        let footer = records.last().unwrap();
+        assert_eq!(footer["type"], "footer");
+        assert_eq!(footer["files_scanned"], analysis.file_analyses().len());
+        assert_eq!(
+            footer["valid"].as_u64().unwrap() + footer["invalid"].as_u64().unwrap(),
+            todo_count as u64
+        );
+        for record in &records[1..=todo_count] {
+            assert_eq!(record["type"], "todo");
+            assert!(record["path"].as_str().unwrap().starts_with("test/"));
+            assert!(record["line"].is_u64());
+        }
+
+        Ok(())
+    }
+}
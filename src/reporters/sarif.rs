@@ -0,0 +1,265 @@
+use super::{invalid_message, portable_path, Reporter};
+use crate::{
+    line_analyzer_types::{BlameInfo, InvalidReason},
+    path_analyzer_types::AnalysisResult,
+    utils::write_json,
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::{io::Write, path::Path};
+use strum::IntoEnumIterator;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+/// Base of the URIs of files in a repository, which code scanning resolves to the checkout root.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// SARIF 2.1.0 log with a single run. Only invalid todos become results; each `InvalidReason`
+/// is a rule.
+pub struct SarifReporter;
+
+#[derive(Serialize)]
+struct SarifLog<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run<'a>>,
+}
+
+#[derive(Serialize)]
+struct Run<'a> {
+    tool: Tool,
+    results: Vec<SarifResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    name: &'static str,
+    short_description: Message,
+    default_configuration: RuleConfiguration,
+}
+
+#[derive(Serialize)]
+struct RuleConfiguration {
+    level: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult<'a> {
+    rule_id: &'static str,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<Properties<'a>>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+}
+
+#[derive(Serialize)]
+struct Properties<'a> {
+    blame: &'a BlameInfo,
+}
+
+impl Reporter for SarifReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        write_json(writer, &SarifLog::new(analysis))
+    }
+}
+
+impl<'a> SarifLog<'a> {
+    fn new(analysis: &'a AnalysisResult) -> Self {
+        let rules = InvalidReason::iter()
+            .map(|reason| Rule {
+                id: reason.code(),
+                name: reason.name(),
+                short_description: Message {
+                    text: reason.description().to_string(),
+                },
+                default_configuration: RuleConfiguration { level: "warning" },
+            })
+            .collect();
+
+        let results = analysis
+            .file_analyses()
+            .iter()
+            .flat_map(|file_analysis| {
+                let metadata = &file_analysis.metadata;
+                let artifact_location = ArtifactLocation {
+                    uri: relative_uri(&metadata.stable_path()),
+                    uri_base_id: metadata.repo_path.is_some().then_some(SRCROOT),
+                };
+
+                file_analysis
+                    .invalids
+                    .iter()
+                    .flatten()
+                    .map(move |invalid| SarifResult {
+                        rule_id: invalid.line_info.reason.code(),
+                        rule_index: InvalidReason::iter()
+                            .position(|reason| reason == invalid.line_info.reason)
+                            .unwrap_or_default(),
+                        level: "warning",
+                        message: Message {
+                            text: invalid_message(invalid),
+                        },
+                        locations: vec![Location {
+                            physical_location: PhysicalLocation {
+                                artifact_location: artifact_location.clone(),
+                                region: Region {
+                                    start_line: invalid.line,
+                                    start_column: invalid.column,
+                                },
+                            },
+                        }],
+                        properties: invalid
+                            .blame_info
+                            .as_ref()
+                            .map(|blame| Properties { blame }),
+                    })
+            })
+            .collect();
+
+        SarifLog {
+            schema: SARIF_SCHEMA,
+            version: SARIF_VERSION,
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: env!("CARGO_PKG_NAME"),
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules,
+                    },
+                },
+                results,
+            }],
+        }
+    }
+}
+
+/// Relative URI reference to `path`, percent-encoding everything but unreserved characters and
+/// separators.
+fn relative_uri(path: &Path) -> String {
+    portable_path(path)
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_file, analyze_lines,
+        path_analyzer_types::{FileAnalysisConfig, FileMetadata},
+    };
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sarif_results_point_at_invalids() -> Result<()> {
+        let file_analysis = analyze_file(
+            Path::new("test/invalid.txt"),
+            &FileAnalysisConfig::default(),
+        )?;
+        let invalid_count = file_analysis.invalids.as_ref().map_or(0, Vec::len);
+        let analysis = AnalysisResult::File(file_analysis);
+
+        let mut output = Vec::new();
+        SarifReporter.report(&analysis, &mut output)?;
+        let sarif: serde_json::Value = serde_json::from_slice(&output)?;
+
+        assert_eq!(sarif["version"], SARIF_VERSION);
+        let run = &sarif["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"].as_array().map(Vec::len),
+            Some(InvalidReason::iter().count())
+        );
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), invalid_count);
+        for result in results {
+            let location = &result["locations"][0]["physicalLocation"];
+            assert_eq!(location["artifactLocation"]["uri"], "test/invalid.txt");
+            assert!(location["artifactLocation"]["uriBaseId"].is_null());
+            assert!(location["region"]["startLine"].as_u64().unwrap() >= 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sarif_uris_are_relative_to_srcroot() -> Result<()> {
+        let metadata = FileMetadata {
+            filepath: PathBuf::from("../project/src/my lib.rs"),
+            repo_path: Some(PathBuf::from("src/my lib.rs")),
+            project_path: None,
+            repository: None,
+            last_modified: Utc::now(),
+        };
+        let file_analysis = analyze_lines(metadata, &b"// todo(0 1): a\n"[..], None)?;
+        let analysis = AnalysisResult::File(file_analysis);
+
+        let mut output = Vec::new();
+        SarifReporter.report(&analysis, &mut output)?;
+        let sarif: serde_json::Value = serde_json::from_slice(&output)?;
+
+        let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/my%20lib.rs");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], SRCROOT);
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use super::Reporter;
+use crate::{
+    line_analyzer_types::InvalidTodoComment,
+    path_analyzer_types::{AnalysisResult, FileAnalysis},
+};
+use anyhow::Result;
+use std::{fs, io::Write, path::Path};
+
+/// rustc-style diagnostics for invalid todos, followed by a per-file summary table.
+pub struct TerminalReporter {
+    /// Enables ANSI colors and OSC 8 hyperlinks.
+    pub styled: bool,
+}
+
+// ANSI SGR codes
+const BOLD: &str = "1";
+const YELLOW: &str = "1;33";
+const BLUE: &str = "1;34";
+const GREEN: &str = "32";
+
+impl Reporter for TerminalReporter {
+    fn report(&self, analysis: &AnalysisResult, writer: &mut dyn Write) -> Result<()> {
+        let file_analyses = analysis.file_analyses();
+
+        for file_analysis in file_analyses {
+            let invalids = match &file_analysis.invalids {
+                Some(invalids) => invalids,
+                None => continue,
+            };
+            // The matched text starts at the marker, so read the whole line back from disk. Staged
+            // or historical content may differ from disk, in which case the line isn't used
+            let source = fs::read_to_string(&file_analysis.metadata.filepath).ok();
+
+            // Lines, columns and spans are 1-based, but a loaded report may hold anything
+            for invalid in invalids {
+                let source_line = source
+                    .as_deref()
+                    .zip(invalid.line.checked_sub(1))
+                    .and_then(|(source, index)| source.lines().nth(index))
+                    .filter(|source_line| source_line.contains(&invalid.line_info.full_text));
+                self.write_diagnostic(writer, file_analysis, invalid, source_line)?;
+            }
+        }
+
+        self.write_summary(writer, file_analyses)
+    }
+}
+
+impl TerminalReporter {
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.styled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Wraps `text` in an OSC 8 hyperlink to the file, if supported.
+    fn link(&self, text: &str, filepath: &Path) -> String {
+        match fs::canonicalize(filepath) {
+            Ok(absolute) if self.styled => format!(
+                "\x1b]8;;file://{}\x1b\\{}\x1b]8;;\x1b\\",
+                absolute.to_string_lossy(),
+                text
+            ),
+            _ => text.to_string(),
+        }
+    }
+
+    fn write_diagnostic(
+        &self,
+        writer: &mut dyn Write,
+        file_analysis: &FileAnalysis,
+        invalid: &InvalidTodoComment,
+        source_line: Option<&str>,
+    ) -> Result<()> {
+        let reason = invalid.line_info.reason;
+        let filepath = &file_analysis.metadata.filepath;
+        let gutter_width = invalid.line.to_string().len();
+        let gutter = self.paint(&format!("{} |", " ".repeat(gutter_width)), BLUE);
+
+        writeln!(
+            writer,
+            "{}{}",
+            self.paint(&format!("warning[{}]", reason.code()), YELLOW),
+            self.paint(&format!(": {}", reason.description()), BOLD)
+        )?;
+        writeln!(
+            writer,
+            "{}{} {}",
+            " ".repeat(gutter_width),
+            self.paint("-->", BLUE),
+            self.link(
+                &format!("{}:{}:{}", filepath.display(), invalid.line, invalid.column),
+                filepath
+            )
+        )?;
+        writeln!(writer, "{}", gutter)?;
+
+        // Fall back to the matched text when the file can't be read or has changed
+        let (source_line, offset) = match source_line {
+            Some(source_line) => (source_line, 0),
+            None => (
+                invalid.line_info.full_text.as_str(),
+                invalid.column.saturating_sub(1),
+            ),
+        };
+        writeln!(
+            writer,
+            "{} {}",
+            self.paint(&format!("{} |", invalid.line), BLUE),
+            source_line
+        )?;
+
+        // Keep tabs so the carets line up with the source line
+        let span = invalid.line_info.span;
+        let padding: String = source_line
+            .chars()
+            .take(span.start.saturating_sub(1).saturating_sub(offset))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(span.end.saturating_sub(span.start).max(1));
+        writeln!(
+            writer,
+            "{} {}{}",
+            gutter,
+            padding,
+            self.paint(&format!("{} {}", carets, reason.name()), YELLOW)
+        )?;
+
+        if let Some(blame_info) = &invalid.blame_info {
+            let note = if blame_info.is_committed() {
+                format!(
+                    "last changed by {} on {} in {:.7}",
+                    blame_info.author.email,
+                    blame_info.author.timestamp.format("%Y-%m-%d"),
+                    blame_info.commit_id
+                )
+            } else {
+                "not committed yet".to_string()
+            };
+            writeln!(writer, "{}", gutter)?;
+            writeln!(
+                writer,
+                "{} {} {}",
+                " ".repeat(gutter_width),
+                self.paint("= note:", BOLD),
+                note
+            )?;
+        }
+
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Lists the files with todos, since most files of a project have none, and totals them.
+    fn write_summary(&self, writer: &mut dyn Write, file_analyses: &[FileAnalysis]) -> Result<()> {
+        let rows: Vec<(String, usize, usize)> = file_analyses
+            .iter()
+            .map(|file_analysis| {
+                (
+                    file_analysis.metadata.filepath.display().to_string(),
+                    file_analysis.valids.as_ref().map_or(0, Vec::len),
+                    file_analysis.invalids.as_ref().map_or(0, Vec::len),
+                )
+            })
+            .filter(|(_, valid, invalid)| valid + invalid > 0)
+            .collect();
+        let total_valid: usize = rows.iter().map(|row| row.1).sum();
+        let total_invalid: usize = rows.iter().map(|row| row.2).sum();
+
+        let path_width = rows
+            .iter()
+            .map(|row| row.0.chars().count())
+            .chain(["File".len()])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            writer,
+            "{}",
+            self.paint(
+                &format!("{:<path_width$}  {:>5}  {:>7}", "File", "Valid", "Invalid"),
+                BOLD
+            )
+        )?;
+        for (filepath, valid, invalid) in &rows {
+            let invalid_cell = format!("{:>7}", invalid);
+            writeln!(
+                writer,
+                "{:<path_width$}  {:>5}  {}",
+                filepath,
+                valid,
+                if *invalid > 0 {
+                    self.paint(&invalid_cell, YELLOW)
+                } else {
+                    invalid_cell
+                }
+            )?;
+        }
+        writeln!(
+            writer,
+            "{}",
+            self.paint(
+                &format!(
+                    "{:<path_width$}  {:>5}  {:>7}",
+                    "Total", total_valid, total_invalid
+                ),
+                BOLD
+            )
+        )?;
+
+        let verdict = format!(
+            "{} file(s) scanned, {} valid and {} invalid todo(s)",
+            file_analyses.len(),
+            total_valid,
+            total_invalid
+        );
+        writeln!(
+            writer,
+            "\n{}",
+            self.paint(&verdict, if total_invalid > 0 { YELLOW } else { GREEN })
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_file, analyze_lines,
+        line_analyzer_types::Span,
+        path_analyzer_types::{DirectoryAnalysis, FileAnalysisConfig, FileMetadata},
+    };
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_terminal_carets_under_marker_content() -> Result<()> {
+        let analysis = AnalysisResult::File(analyze_file(
+            Path::new("test/invalid.txt"),
+            &FileAnalysisConfig::default(),
+        )?);
+
+        let mut output = Vec::new();
+        TerminalReporter { styled: false }.report(&analysis, &mut output)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.contains("warning[TL002]"));
+        assert!(output.contains("--> test/invalid.txt:2:1"));
+        assert!(output.contains("2 | // todo(0 1): 2\n  |        ^^^^^ invalid-delimiter-content"));
+        assert!(!output.contains('\x1b'), "Expected no escape codes");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_zero_positions_of_loaded_report() -> Result<()> {
+        let mut file_analysis = analyze_file(
+            Path::new("test/invalid.txt"),
+            &FileAnalysisConfig::default(),
+        )?;
+        for invalid in file_analysis.invalids.iter_mut().flatten() {
+            invalid.line = 0;
+            invalid.column = 0;
+            invalid.line_info.span = Span { start: 0, end: 0 };
+        }
+
+        let mut output = Vec::new();
+        TerminalReporter { styled: false }
+            .report(&AnalysisResult::File(file_analysis), &mut output)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.contains("--> test/invalid.txt:0:0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_summary_lists_files_with_todos() -> Result<()> {
+        let file_analysis = |filepath: &str, content: &str| {
+            let metadata = FileMetadata {
+                filepath: PathBuf::from(filepath),
+                repo_path: None,
+                project_path: None,
+                repository: None,
+                last_modified: Utc::now(),
+            };
+            analyze_lines(metadata, content.as_bytes(), None)
+        };
+        let analysis = AnalysisResult::Directory(DirectoryAnalysis {
+            total_files_scanned: 2,
+            last_scan_on: Utc::now(),
+            file_analyses: vec![
+                file_analysis("with_todo.rs", "// todo: a\n")?,
+                file_analysis("without_todo.rs", "fn main() {}\n")?,
+            ],
+        });
+
+        let mut output = Vec::new();
+        TerminalReporter { styled: false }.report(&analysis, &mut output)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.contains("with_todo.rs"));
+        assert!(!output.contains("without_todo.rs"));
+        assert!(output.contains("Total"));
+        assert!(output.contains("2 file(s) scanned, 1 valid and 0 invalid todo(s)"));
+        Ok(())
+    }
+}
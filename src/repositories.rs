@@ -0,0 +1,93 @@
+use git2::Repository;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Finds the repository each file under a scan root belongs to: the one the root is in, or a
+/// nested repository or submodule below the root. Nested repositories are opened once.
+pub struct Repositories<'a> {
+    root: PathBuf,
+    root_repo: Option<&'a Repository>,
+    /// Root of the nested repository each directory seen so far is in, `None` for the root's.
+    dirs: HashMap<PathBuf, Option<PathBuf>>,
+    nested: HashMap<PathBuf, Repository>,
+}
+
+impl<'a> Repositories<'a> {
+    pub fn new(root: &Path, root_repo: Option<&'a Repository>) -> Self {
+        Repositories {
+            root: root.to_path_buf(),
+            root_repo,
+            dirs: HashMap::new(),
+            nested: HashMap::new(),
+        }
+    }
+
+    /// The repository `filepath` belongs to. `filepath` is expected to start with the root, like
+    /// the paths of a directory walk.
+    pub fn repo_for(&mut self, filepath: &Path) -> Option<&Repository> {
+        let nested_root = filepath.parent().and_then(|dir| self.nested_root(dir));
+        match nested_root {
+            Some(nested_root) => self.nested.get(&nested_root),
+            None => self.root_repo,
+        }
+    }
+
+    fn nested_root(&mut self, dir: &Path) -> Option<PathBuf> {
+        if let Some(nested_root) = self.dirs.get(dir) {
+            return nested_root.clone();
+        }
+
+        // Submodules have a `.git` file pointing into the parent's `.git/modules` instead of a
+        // directory, which `Repository::open` follows
+        let nested_root = if dir == self.root || !dir.starts_with(&self.root) {
+            None
+        } else if let Some(repo) = dir
+            .join(".git")
+            .exists()
+            .then(|| Repository::open(dir).ok())
+            .flatten()
+        {
+            self.nested.insert(dir.to_path_buf(), repo);
+            Some(dir.to_path_buf())
+        } else {
+            dir.parent().and_then(|parent| self.nested_root(parent))
+        };
+        self.dirs.insert(dir.to_path_buf(), nested_root.clone());
+        nested_root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+    use std::fs;
+
+    #[test]
+    fn test_repo_for_nested_repository() {
+        let (dir, repo) = test_repo::init();
+        let inner = Repository::init(dir.path().join("vendor/inner")).unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+
+        let mut repositories = Repositories::new(dir.path(), Some(&repo));
+        let workdir = |repo: Option<&Repository>| {
+            repo.and_then(|repo| repo.workdir())
+                .map(|workdir| workdir.canonicalize().unwrap())
+        };
+
+        assert_eq!(
+            workdir(repositories.repo_for(&dir.path().join("vendor/inner/src/a.rs"))),
+            workdir(Some(&inner))
+        );
+        assert_eq!(
+            workdir(repositories.repo_for(&dir.path().join("src/a.rs"))),
+            workdir(Some(&repo))
+        );
+        assert_eq!(
+            workdir(repositories.repo_for(&dir.path().join("a.rs"))),
+            workdir(Some(&repo))
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    analyze_lines,
+    blame_types::BlameConfig,
+    path_analyzer_types::{DirectoryAnalysis, FileAnalysis, FileBlameContext, FileMetadata},
+    utils::repository_root,
+};
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use std::path::Path;
+
+/// Analyzes every file in the tree of `rev` (a commit, tag, branch, ...) without checking it out.
+/// Only files under `prefix` (relative to the repository root) are analyzed, and with `blame`,
+/// todos are blamed as of that commit. Paths are relative to the repository root, and
+/// `last_modified` is the commit's time.
+pub fn analyze_rev(
+    repo: &Repository,
+    rev: &str,
+    prefix: &Path,
+    blame: Option<&BlameConfig>,
+) -> Result<DirectoryAnalysis> {
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("Failed to resolve revision '{}'", rev))?;
+    let tree = commit.tree().context("Failed to read commit tree")?;
+    let last_modified = Utc
+        .timestamp_opt(commit.time().seconds(), 0)
+        .single()
+        .with_context(|| format!("Invalid timestamp: {}", commit.time().seconds()))?;
+
+    let mut file_analyses: Vec<FileAnalysis> = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        // Submodules show up as commits and are skipped along with anything else not a file
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        let filepath = Path::new(root).join(String::from_utf8_lossy(entry.name_bytes()).as_ref());
+        if !filepath.starts_with(prefix) {
+            return TreeWalkResult::Ok;
+        }
+        let blob = match entry
+            .to_object(repo)
+            .and_then(|object| object.peel_to_blob())
+        {
+            Ok(blob) => blob,
+            Err(_) => return TreeWalkResult::Ok,
+        };
+
+        let file_blame_context = blame.and_then(|config| {
+            FileBlameContext::at_commit(repo, &filepath, commit.id(), config).ok()
+        });
+        let metadata = FileMetadata {
+            repo_path: Some(filepath.clone()),
+            project_path: None,
+            repository: Some(repository_root(repo)),
+            filepath,
+            last_modified,
+        };
+        // Binary and non UTF-8 blobs fail to analyze, same as on disk
+        if let Ok(file_analysis) =
+            analyze_lines(metadata, blob.content(), file_blame_context.as_ref())
+        {
+            file_analyses.push(file_analysis);
+        }
+        TreeWalkResult::Ok
+    })
+    .context("Failed to walk commit tree")?;
+
+    Ok(DirectoryAnalysis {
+        total_files_scanned: file_analyses.len(),
+        last_scan_on: Utc::now(),
+        file_analyses,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+
+    #[test]
+    fn test_analyze_rev_with_blame_as_of_commit() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        test_repo::commit(
+            &repo,
+            &[("src/a.rs", "// todo: a\n"), ("b.rs", "// todo: b\n")],
+            "first",
+        );
+        repo.tag_lightweight("v1", &repo.revparse_single("HEAD")?, false)?;
+        // Blaming the deleted file at HEAD would fail
+        let mut index = repo.index()?;
+        index.remove_path(Path::new("src/a.rs"))?;
+        index.write()?;
+        test_repo::commit(&repo, &[("b.rs", "// todo: c\n")], "second");
+
+        let blame_config = BlameConfig::default();
+        let analysis = analyze_rev(&repo, "v1", Path::new("src"), Some(&blame_config))?;
+
+        assert_eq!(analysis.file_analyses.len(), 1);
+        let file_analysis = &analysis.file_analyses[0];
+        assert_eq!(file_analysis.metadata.filepath, Path::new("src/a.rs"));
+        let valids = file_analysis.valids.as_ref().unwrap();
+        assert_eq!(valids.len(), 1);
+        assert!(valids[0].blame_info.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{
+    blame_types::BlameConfig,
+    line_analyzer::ANALYZER_VERSION,
+    path_analyzer_types::FileAnalysis,
+    scan_cache_types::{CachedAnalysis, ScanCacheFile, ScannedFile},
+    utils::paths_changed_since,
+};
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use git2::{Oid, Repository};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+/// Format of the cache file. Changes to the analysis bump `ANALYZER_VERSION` instead.
+const SCAN_CACHE_VERSION: u32 = 2;
+
+/// Reuses the analysis of files unmodified since an earlier scan. A file is unmodified if its
+/// mtime and size are the same, or failing that its content hash, and its blame can't have
+/// changed: HEAD is the same, or moved forward through commits that don't touch the file.
+pub struct ScanCache {
+    path: PathBuf,
+    file: ScanCacheFile,
+    /// Paths changed between a cached HEAD and the current one, per pair looked up so far, see
+    /// `paths_changed_since`.
+    changed_paths: HashMap<(Oid, Oid), Option<HashSet<PathBuf>>>,
+    changed: bool,
+}
+
+impl ScanCache {
+    /// Loads the cache at `path` for analyses blamed with `blame_config`.
+    pub fn load(path: &Path, blame_config: Option<&BlameConfig>) -> Self {
+        let options = ScanCache::options(blame_config);
+
+        // The cache is only an optimization, so an unreadable or outdated one is started over
+        let file = fs::read(path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<ScanCacheFile>(&content).ok())
+            .filter(|file| file.version == SCAN_CACHE_VERSION && file.options == options)
+            .unwrap_or_else(|| ScanCacheFile {
+                version: SCAN_CACHE_VERSION,
+                options,
+                files: BTreeMap::new(),
+            });
+
+        ScanCache {
+            path: path.to_path_buf(),
+            file,
+            changed_paths: HashMap::new(),
+            changed: false,
+        }
+    }
+
+    /// Analyses change with the analyzer, and with every blame option including ignored
+    /// revisions. The todolint version is included too, in case a change to the analysis didn't
+    /// bump `ANALYZER_VERSION`. `--base` doesn't count: it only narrows down what's reported,
+    /// after the whole analysis is cached.
+    fn options(blame_config: Option<&BlameConfig>) -> String {
+        let blame = blame_config
+            .map(|config| {
+                let mut ignore_revs: Vec<String> =
+                    config.ignore_revs.iter().map(Oid::to_string).collect();
+                ignore_revs.sort();
+                format!(
+                    "{} ignore-revs={}",
+                    config.cache_key(),
+                    ignore_revs.join(",")
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "todolint={} analyzer={} {}",
+            env!("CARGO_PKG_VERSION"),
+            ANALYZER_VERSION,
+            blame
+        )
+    }
+
+    /// The cached analysis of `file` if it's unmodified. Without its `content`, only the mtime
+    /// and size are compared. `repo` is the repository the file belongs to.
+    pub fn get(
+        &mut self,
+        file: &ScannedFile,
+        content: Option<&[u8]>,
+        repo: Option<&Repository>,
+    ) -> Option<FileAnalysis> {
+        let cached = self.file.files.get_mut(&file.path)?;
+        let unmodified = match content {
+            // Some filesystems only record mtimes to the nearest 2 seconds
+            None => {
+                cached.modified == file.modified
+                    && cached.size == file.size
+                    && cached.modified + Duration::seconds(2) < cached.last_scan_on
+            }
+            Some(content) => cached.hash == hash(content),
+        };
+        if !unmodified || !same_blame(cached, file, repo, &mut self.changed_paths) {
+            return None;
+        }
+
+        let head = file.head.map(|head| head.to_string());
+        if content.is_some() || cached.head != head {
+            cached.modified = file.modified;
+            cached.size = file.size;
+            cached.head = head;
+            if content.is_some() {
+                cached.last_scan_on = Utc::now();
+            }
+            self.changed = true;
+        }
+        Some(cached.analysis.clone())
+    }
+
+    /// Caches the analysis of `file`, whose content is `content`.
+    pub fn insert(&mut self, file: &ScannedFile, content: &[u8], analysis: &FileAnalysis) {
+        self.file.files.insert(
+            file.path.clone(),
+            CachedAnalysis {
+                modified: file.modified,
+                size: file.size,
+                hash: hash(content),
+                last_scan_on: Utc::now(),
+                head: file.head.map(|head| head.to_string()),
+                blob: file.blob.map(|blob| blob.to_string()),
+                analysis: analysis.clone(),
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Writes the cache if anything changed, leaving out files that no longer exist.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        self.file.files.retain(|path, _| path.is_file());
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec(&self.file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+impl ScannedFile {
+    /// `repo` is the repository the file belongs to, with the file's path relative to its root.
+    pub fn new(
+        filepath: &Path,
+        metadata: &Metadata,
+        repo: Option<(&Repository, &Path)>,
+    ) -> Result<Self> {
+        let head =
+            repo.and_then(|(repo, _)| repo.head().and_then(|head| head.peel_to_commit()).ok());
+        let blob = repo
+            .zip(head.as_ref())
+            .and_then(|((_, repo_path), head)| head.tree().ok()?.get_path(repo_path).ok())
+            .map(|entry| entry.id());
+
+        Ok(ScannedFile {
+            path: fs::canonicalize(filepath)?,
+            repo_path: repo.map(|(_, repo_path)| repo_path.to_path_buf()),
+            modified: metadata.modified()?.into(),
+            size: metadata.len(),
+            head: head.map(|head| head.id()),
+            blob,
+        })
+    }
+}
+
+/// Whether the blame of `file` is still the one it was cached with: HEAD is the same, or moved
+/// forward without touching the file, not even to change it back.
+fn same_blame(
+    cached: &CachedAnalysis,
+    file: &ScannedFile,
+    repo: Option<&Repository>,
+    changed_paths: &mut HashMap<(Oid, Oid), Option<HashSet<PathBuf>>>,
+) -> bool {
+    if cached.head == file.head.map(|head| head.to_string()) {
+        return true;
+    }
+    if cached.blob != file.blob.map(|blob| blob.to_string()) {
+        return false;
+    }
+    let cached_head = cached
+        .head
+        .as_deref()
+        .and_then(|head| Oid::from_str(head).ok());
+    match (repo, cached_head, file.head, &file.repo_path) {
+        (Some(repo), Some(cached_head), Some(head), Some(repo_path)) => changed_paths
+            .entry((cached_head, head))
+            .or_insert_with(|| paths_changed_since(repo, cached_head, head).ok().flatten())
+            .as_ref()
+            .is_some_and(|changed_paths| !changed_paths.contains(repo_path)),
+        _ => false,
+    }
+}
+
+fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path_analyzer_types::FileMetadata, utils::test_repo};
+    use std::{fs::File, time::SystemTime};
+
+    fn scanned_file(repo: &Repository, path: &str) -> Result<ScannedFile> {
+        let filepath = repo.workdir().unwrap().join(path);
+        ScannedFile::new(
+            &filepath,
+            &fs::metadata(&filepath)?,
+            Some((repo, Path::new(path))),
+        )
+    }
+
+    #[test]
+    fn test_scan_cache_reuses_unmodified_files() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n"), ("b.rs", "")], "first");
+        let cache_path = dir.path().join("scan-cache.json");
+        let content = fs::read(dir.path().join("a.rs"))?;
+        let analysis = FileAnalysis {
+            metadata: FileMetadata {
+                filepath: PathBuf::from("a.rs"),
+                repo_path: None,
+                project_path: None,
+                repository: None,
+                last_modified: Utc::now(),
+            },
+            valids: None,
+            invalids: None,
+        };
+
+        let mut cache = ScanCache::load(&cache_path, None);
+        cache.insert(&scanned_file(&repo, "a.rs")?, &content, &analysis);
+        cache.save()?;
+
+        // The file was just written, so its mtime can't be trusted yet, but its hash can
+        let mut cache = ScanCache::load(&cache_path, None);
+        let file = scanned_file(&repo, "a.rs")?;
+        assert!(cache.get(&file, None, Some(&repo)).is_none());
+        assert!(cache.get(&file, Some(&content), Some(&repo)).is_some());
+
+        // Once older than the scan, the mtime is enough, even after unrelated commits
+        File::options()
+            .write(true)
+            .open(dir.path().join("a.rs"))?
+            .set_modified(SystemTime::now() - std::time::Duration::from_secs(60))?;
+        let file = scanned_file(&repo, "a.rs")?;
+        assert!(cache.get(&file, Some(&content), Some(&repo)).is_some());
+        test_repo::commit(&repo, &[("b.rs", "// todo: b\n")], "second");
+        let file = scanned_file(&repo, "a.rs")?;
+        assert!(cache.get(&file, None, Some(&repo)).is_some());
+
+        // A commit touching the file invalidates it
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n// todo: c\n")], "third");
+        let file = scanned_file(&repo, "a.rs")?;
+        assert!(cache.get(&file, None, Some(&repo)).is_none());
+        assert!(cache.get(&file, Some(&content), Some(&repo)).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_cache_invalidated_by_reverted_change() -> Result<()> {
+        let (dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "first");
+        let cache_path = dir.path().join("scan-cache.json");
+        let content = fs::read(dir.path().join("a.rs"))?;
+        let analysis = FileAnalysis {
+            metadata: FileMetadata {
+                filepath: PathBuf::from("a.rs"),
+                repo_path: Some(PathBuf::from("a.rs")),
+                project_path: None,
+                repository: None,
+                last_modified: Utc::now(),
+            },
+            valids: None,
+            invalids: None,
+        };
+
+        let mut cache = ScanCache::load(&cache_path, None);
+        cache.insert(&scanned_file(&repo, "a.rs")?, &content, &analysis);
+        cache.save()?;
+
+        // Same content and blob as cached, but the revert is what its lines are blamed on now
+        test_repo::commit(&repo, &[("a.rs", "// todo: b\n")], "change");
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "revert");
+        let mut cache = ScanCache::load(&cache_path, None);
+        let file = scanned_file(&repo, "a.rs")?;
+        assert!(cache.get(&file, Some(&content), Some(&repo)).is_none());
+        Ok(())
+    }
+}
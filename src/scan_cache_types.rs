@@ -0,0 +1,43 @@
+use crate::path_analyzer_types::FileAnalysis;
+use chrono::{DateTime, Utc};
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Analyses of earlier scans, so files unmodified since aren't analyzed again.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScanCacheFile {
+    pub version: u32,
+    /// The todolint version and blame options the analyses were made with, see
+    /// `ScanCache::options`.
+    pub options: String,
+    /// Per canonical path.
+    pub files: BTreeMap<PathBuf, CachedAnalysis>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedAnalysis {
+    pub modified: DateTime<Utc>,
+    pub size: u64,
+    /// SHA-256 of the content, to tell whether a file whose mtime changed was actually modified.
+    pub hash: String,
+    /// When the file was last analyzed or found unchanged. The mtime is only trusted if it's
+    /// older, since a file modified right after it was read may keep the same mtime.
+    pub last_scan_on: DateTime<Utc>,
+    /// HEAD of the file's repository and the file's blob there, which its blame depends on.
+    pub head: Option<String>,
+    pub blob: Option<String>,
+    pub analysis: FileAnalysis,
+}
+
+/// What a file on disk is compared with its cached analysis by.
+pub struct ScannedFile {
+    /// Canonical path.
+    pub path: PathBuf,
+    /// Path relative to the root of the file's repository, if any.
+    pub repo_path: Option<PathBuf>,
+    pub modified: DateTime<Utc>,
+    pub size: u64,
+    pub head: Option<Oid>,
+    pub blob: Option<Oid>,
+}
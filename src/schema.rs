@@ -0,0 +1,103 @@
+use schemars::{
+    schema::{RootSchema, Schema},
+    schema_for, JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON (and BSON) report format, written as `schema_version` at the top level.
+///
+/// Compatibility rules:
+/// - Within a version, fields may be added, but existing fields are never removed, renamed,
+///   moved or retyped. Added fields are optional, so consumers of the same version keep working.
+/// - Any other change to the output bumps the version.
+///
+/// The schema of every version is committed as `schema/v{version}.json`. Version 2 replaced the
+/// blame `email` and `timestamp` with the `author` and `committer` identities and added the
+/// blamed commit.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Top level of a JSON or BSON report: the analysis, tagged with the format version.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct VersionedReport<A> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub analysis: A,
+}
+
+impl<A> VersionedReport<A> {
+    pub fn new(analysis: A) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            analysis,
+        }
+    }
+}
+
+/// Rejects reports of another schema version before their content is parsed, so the error says
+/// what's wrong instead of which field failed to parse.
+pub fn check_schema_version(schema_version: Option<i64>) -> anyhow::Result<()> {
+    let current = SCHEMA_VERSION as i64;
+    match schema_version {
+        Some(version) if version == current => Ok(()),
+        Some(version) if version > current => anyhow::bail!(
+            "Report has schema version {}, but only up to {} is supported",
+            version,
+            SCHEMA_VERSION
+        ),
+        Some(version) => anyhow::bail!(
+            "Report has schema version {}, which is no longer supported; scan again to get \
+             version {}",
+            version,
+            SCHEMA_VERSION
+        ),
+        None => anyhow::bail!("Report has no schema_version"),
+    }
+}
+
+/// JSON Schema of a report, as written by the JSON reporter.
+pub fn report_schema() -> RootSchema {
+    let mut schema = schema_for!(VersionedReport<crate::path_analyzer_types::AnalysisResult>);
+    if let Some(metadata) = schema.schema.metadata.as_mut() {
+        metadata.title = Some(format!("todolint report v{}", SCHEMA_VERSION));
+    }
+
+    // The analysis is flattened next to `schema_version`, but schemars still closes each
+    // variant's object, which would reject `schema_version`
+    let variants = schema
+        .schema
+        .subschemas
+        .as_mut()
+        .and_then(|subschemas| subschemas.one_of.as_mut());
+    for variant in variants.into_iter().flatten() {
+        if let Schema::Object(variant) = variant {
+            if let Some(object) = variant.object.as_mut() {
+                object.additional_properties = None;
+            }
+        }
+    }
+
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_report_schema_matches_committed_schema() -> anyhow::Result<()> {
+        let committed: serde_json::Value = serde_json::from_str(&fs::read_to_string(format!(
+            "schema/v{}.json",
+            SCHEMA_VERSION
+        ))?)?;
+        let generated = serde_json::to_value(report_schema())?;
+
+        assert!(
+            committed == generated,
+            "Report schema changed. Check the compatibility rules on `SCHEMA_VERSION`, then \
+             update schema/v{}.json with the output of `todolint schema`.",
+            SCHEMA_VERSION
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    history::{commit_time, walk_history},
+    reporters::escape_xml,
+    trend_types::{TrendPoint, TrendSampling},
+};
+use anyhow::Result;
+use chrono::Datelike;
+use git2::{Oid, Repository};
+use std::{collections::HashMap, io::Write, path::PathBuf};
+
+/// Todo counts at every commit of the first-parent history up to `rev`, oldest first, sampled
+/// with `sampling`. The last commit is always included when sampling every Nth commit.
+pub fn todo_trend(
+    repo: &Repository,
+    rev: &str,
+    sampling: TrendSampling,
+    every: usize,
+) -> Result<Vec<TrendPoint>> {
+    let tags = tags_by_commit(repo)?;
+
+    let mut points = Vec::new();
+    let mut file_counts: HashMap<PathBuf, (usize, usize)> = HashMap::new();
+    let (mut valid, mut invalid) = (0, 0);
+
+    walk_history(repo, rev, |commit, file_analyses| {
+        for file_analysis in file_analyses {
+            let counts = (
+                file_analysis.valids.as_ref().map_or(0, Vec::len),
+                file_analysis.invalids.as_ref().map_or(0, Vec::len),
+            );
+            let (old_valid, old_invalid) = file_counts
+                .insert(file_analysis.metadata.filepath, counts)
+                .unwrap_or_default();
+            valid = valid + counts.0 - old_valid;
+            invalid = invalid + counts.1 - old_invalid;
+        }
+
+        points.push(TrendPoint {
+            commit: commit.id().to_string(),
+            timestamp: commit_time(commit)?,
+            tags: tags.get(&commit.id()).cloned().unwrap_or_default(),
+            valid,
+            invalid,
+        });
+        Ok(())
+    })?;
+
+    Ok(sample(points, sampling, every))
+}
+
+fn tags_by_commit(repo: &Repository) -> Result<HashMap<Oid, Vec<String>>> {
+    let mut tags: HashMap<Oid, Vec<String>> = HashMap::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let commit = repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit());
+        // Tags can point at trees or blobs, which aren't part of the history
+        if let Ok(commit) = commit {
+            tags.entry(commit.id()).or_default().push(name.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+fn sample(points: Vec<TrendPoint>, sampling: TrendSampling, every: usize) -> Vec<TrendPoint> {
+    let last = points.len().saturating_sub(1);
+    let week = |point: &TrendPoint| {
+        let week = point.timestamp.iso_week();
+        (week.year(), week.week())
+    };
+
+    let mut sampled = Vec::new();
+    for (index, point) in points.iter().enumerate() {
+        let keep = match sampling {
+            TrendSampling::Commit => index % every.max(1) == 0 || index == last,
+            TrendSampling::Tag => !point.tags.is_empty(),
+            TrendSampling::Week => points
+                .get(index + 1)
+                .is_none_or(|next| week(next) != week(point)),
+        };
+        if keep {
+            sampled.push(point.clone());
+        }
+    }
+    sampled
+}
+
+pub fn write_trend_csv(points: &[TrendPoint], writer: &mut dyn Write) -> Result<()> {
+    writeln!(writer, "commit,timestamp,tags,valid,invalid")?;
+    for point in points {
+        writeln!(
+            writer,
+            "{},{},\"{}\",{},{}",
+            point.commit,
+            point.timestamp.to_rfc3339(),
+            point.tags.join(" ").replace('"', "\"\""),
+            point.valid,
+            point.invalid
+        )?;
+    }
+    Ok(())
+}
+
+/// A standalone SVG line chart of valid and invalid todos over the sampled commits.
+pub fn write_trend_svg(points: &[TrendPoint], writer: &mut dyn Write) -> Result<()> {
+    const WIDTH: usize = 800;
+    const HEIGHT: usize = 320;
+    const LEFT: usize = 50;
+    const RIGHT: usize = 20;
+    const TOP: usize = 30;
+    const BOTTOM: usize = 40;
+
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let max = points
+        .iter()
+        .map(|point| point.valid.max(point.invalid))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let x = |index: usize| LEFT + index * plot_width / points.len().saturating_sub(1).max(1);
+    let y = |count: usize| TOP + plot_height - count * plot_height / max;
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(
+        writer,
+        r##"<rect width="{WIDTH}" height="{HEIGHT}" fill="#fff"/>"##
+    )?;
+    writeln!(
+        writer,
+        r##"<path d="M{LEFT} {TOP}V{} H{}" fill="none" stroke="#888"/>"##,
+        TOP + plot_height,
+        LEFT + plot_width
+    )?;
+    writeln!(
+        writer,
+        r#"<text x="{}" y="{}" text-anchor="end">{}</text><text x="{}" y="{}" text-anchor="end">0</text>"#,
+        LEFT - 6,
+        TOP + 4,
+        max,
+        LEFT - 6,
+        TOP + plot_height + 4
+    )?;
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        writeln!(
+            writer,
+            r#"<text x="{LEFT}" y="{}">{}</text><text x="{}" y="{}" text-anchor="end">{}</text>"#,
+            HEIGHT - BOTTOM + 18,
+            first.timestamp.format("%Y-%m-%d"),
+            LEFT + plot_width,
+            HEIGHT - BOTTOM + 18,
+            last.timestamp.format("%Y-%m-%d")
+        )?;
+    } else {
+        writeln!(writer, r#"<text x="{LEFT}" y="{TOP}">No data</text>"#)?;
+    }
+
+    // Name, color and count of each line
+    type Series = (&'static str, &'static str, fn(&TrendPoint) -> usize);
+    let series: [Series; 2] = [
+        ("valid", "#16a34a", |point| point.valid),
+        ("invalid", "#dc2626", |point| point.invalid),
+    ];
+    for (index, (name, color, count)) in series.into_iter().enumerate() {
+        let coordinates: Vec<String> = points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| format!("{},{}", x(index), y(count(point))))
+            .collect();
+        writeln!(
+            writer,
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+            coordinates.join(" ")
+        )?;
+        for (index, point) in points.iter().enumerate() {
+            let label = if point.tags.is_empty() {
+                format!("{:.7}", point.commit)
+            } else {
+                point.tags.join(", ")
+            };
+            writeln!(
+                writer,
+                r#"<circle cx="{}" cy="{}" r="3" fill="{color}"><title>{} {}: {} {}</title></circle>"#,
+                x(index),
+                y(count(point)),
+                escape_xml(&label),
+                point.timestamp.format("%Y-%m-%d"),
+                count(point),
+                name
+            )?;
+        }
+        writeln!(
+            writer,
+            r#"<rect x="{}" y="8" width="10" height="10" fill="{color}"/><text x="{}" y="17">{name}</text>"#,
+            LEFT + index * 80,
+            LEFT + index * 80 + 14
+        )?;
+    }
+
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_repo;
+
+    #[test]
+    fn test_todo_trend() -> Result<()> {
+        let (_dir, repo) = test_repo::init();
+        test_repo::commit(&repo, &[("a.rs", "// todo: a\n")], "first");
+        test_repo::commit(&repo, &[("b.rs", "// todo(): b\n")], "second");
+        repo.tag_lightweight("v1", &repo.revparse_single("HEAD")?, false)?;
+        test_repo::commit(&repo, &[("a.rs", "\n")], "third");
+
+        let counts = |points: Vec<TrendPoint>| -> Vec<(usize, usize)> {
+            points
+                .iter()
+                .map(|point| (point.valid, point.invalid))
+                .collect()
+        };
+        assert_eq!(
+            counts(todo_trend(&repo, "HEAD", TrendSampling::Commit, 1)?),
+            vec![(1, 0), (1, 1), (0, 1)]
+        );
+        assert_eq!(
+            counts(todo_trend(&repo, "HEAD", TrendSampling::Commit, 5)?),
+            vec![(1, 0), (0, 1)]
+        );
+        let tagged = todo_trend(&repo, "HEAD", TrendSampling::Tag, 1)?;
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].tags, vec!["v1"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Todo counts as of one commit.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrendPoint {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+/// Which commits of the history are sampled.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TrendSampling {
+    /// Every Nth commit, see `--every`.
+    Commit,
+    /// Tagged commits.
+    Tag,
+    /// The last commit of each week.
+    Week,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TrendFormat {
+    Csv,
+    Json,
+    Svg,
+}
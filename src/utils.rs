@@ -1,6 +1,161 @@
+use anyhow::Context;
+use git2::{Oid, Repository};
 use serde_json;
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
 
-pub fn print_json<T: serde::Serialize>(item: &T) {
-    let json = serde_json::to_string_pretty(item).unwrap();
-    println!("{}\n", json);
+pub fn write_json<T: serde::Serialize>(writer: &mut dyn Write, item: &T) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, item)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// `path` relative to the root of the repository's working tree, e.g. to look it up in a tree
+/// or the index.
+pub fn repo_relative_path(repo: &Repository, path: &Path) -> anyhow::Result<PathBuf> {
+    let workdir = repo
+        .workdir()
+        .context("Bare repositories have no working tree")?;
+    fs::canonicalize(path)?
+        .strip_prefix(fs::canonicalize(workdir)?)
+        .map(Path::to_path_buf)
+        .with_context(|| format!("'{}' is outside of the repository", path.display()))
+}
+
+/// Strips `.` components, e.g. a leading `./`.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+/// Root of the repository's working tree, or its git directory if it's bare.
+pub fn repository_root(repo: &Repository) -> PathBuf {
+    repo.workdir()
+        .unwrap_or_else(|| repo.path())
+        .components()
+        .collect()
+}
+
+/// Root of the working tree of the top-level repository `repo` is nested in, such as the
+/// superproject of a submodule, or of `repo` itself if it isn't nested. Canonical, so paths can be
+/// made relative to it. A repository that ignores the nested one isn't counted.
+pub fn project_root(repo: &Repository) -> anyhow::Result<PathBuf> {
+    let mut root = fs::canonicalize(repository_root(repo))?;
+    while let Some(outer) = root
+        .parent()
+        .and_then(|parent| Repository::discover(parent).ok())
+    {
+        let outer_root = match outer.workdir() {
+            Some(workdir) => fs::canonicalize(workdir)?,
+            None => break,
+        };
+        // The trailing slash makes patterns only matching directories apply
+        let nested = root.strip_prefix(&outer_root)?.join("");
+        if outer.status_should_ignore(&nested).unwrap_or(false) {
+            break;
+        }
+        root = outer_root;
+    }
+    Ok(root)
+}
+
+/// Directory shared by all worktrees of a repository, holding hooks, config and objects. Same as
+/// `repo.path()` except in linked worktrees.
+pub fn common_dir(repo: &Repository) -> PathBuf {
+    let commondir = repo.path().join("commondir");
+    match fs::read_to_string(commondir) {
+        Ok(commondir) if repo.is_worktree() => {
+            let common_dir = repo.path().join(commondir.trim());
+            fs::canonicalize(&common_dir).unwrap_or(common_dir)
+        }
+        _ => repo.path().to_path_buf(),
+    }
+}
+
+/// Paths changed by any commit `head` descends from that `since` doesn't, relative to the
+/// repository root. A file changed and then changed back is included, since its blame changed.
+/// `None` if `head` doesn't descend from `since`.
+pub fn paths_changed_since(
+    repo: &Repository,
+    since: Oid,
+    head: Oid,
+) -> anyhow::Result<Option<HashSet<PathBuf>>> {
+    if since != head && !repo.graph_descendant_of(head, since)? {
+        return Ok(None);
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    revwalk.hide(since)?;
+    let mut paths = HashSet::new();
+    for commit in revwalk {
+        let commit = repo.find_commit(commit?)?;
+        let tree = commit.tree()?;
+        // A merge changes whatever differs from any of its parents
+        let parent_trees = commit
+            .parents()
+            .map(|parent| parent.tree().map(Some))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parent_trees = if parent_trees.is_empty() {
+            vec![None]
+        } else {
+            parent_trees
+        };
+        for parent_tree in parent_trees {
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+            for delta in diff.deltas() {
+                paths.extend(delta.old_file().path().map(Path::to_path_buf));
+                paths.extend(delta.new_file().path().map(Path::to_path_buf));
+            }
+        }
+    }
+    Ok(Some(paths))
+}
+
+/// Throwaway git repositories for tests.
+#[cfg(test)]
+pub mod test_repo {
+    use git2::{Commit, Oid, Repository, Signature};
+    use std::{fs, path::Path};
+    use tempfile::TempDir;
+
+    /// The directory has to outlive the repository.
+    pub fn init() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        (dir, repo)
+    }
+
+    /// Writes `files` to the working tree, stages them and commits on top of HEAD.
+    pub fn commit(repo: &Repository, files: &[(&str, &str)], message: &str) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (path, content) in files {
+            let full_path = workdir.join(path);
+            fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            fs::write(&full_path, content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
 }